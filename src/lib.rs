@@ -1,5 +1,8 @@
 pub mod poker;
-pub use crate::poker::{solve_ranges, EquityResult, Game, Hand, Range};
+pub use crate::poker::{
+    equity_distributions, solve_equity, solve_ranges, Card, Deck, EquityDistribution, EquityResult,
+    Game, GameRules, Hand, Range, RangeFormat, RangeGrid,
+};
 
 use std::alloc::{alloc as raw_alloc, dealloc as raw_dealloc, Layout};
 use std::cell::RefCell;
@@ -1,17 +1,52 @@
 mod utils;
 use crate::utils::prettify;
-use poker_solver::Game;
+use poker_solver::poker::{
+    all_in_ev, class_ranking, icm, ActionRule, ActionTree, CfrSolver, Holdem, NodeKind,
+    PreflopEquity, PushFold, ShoveSpot, StrategyFile, TreeConfig,
+};
+use poker_solver::{
+    equity_distributions, solve_equity, solve_ranges, EquityDistribution, Game, Hand, Range,
+    RangeFormat,
+};
 use std::env;
 use std::io::stdout;
 use std::io::Write;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let empty = &String::new();
-    let community = args.get(1).unwrap_or(empty);
-    let hand_a = args.get(2).unwrap_or(empty);
-    let hand_b = args.get(3).unwrap_or(empty);
-    solve(hand_a, hand_b, community);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("range") => range(&args[1..]),
+        Some("dist") => dist(&mut args[1..].to_vec()),
+        Some("tree") => tree(&args[1..]),
+        Some("pushfold") => pushfold(&mut args[1..].to_vec()),
+        Some("icm") => icm_cli(&mut args[1..].to_vec()),
+        Some("shove") => shove(&mut args[1..].to_vec()),
+        Some("postflop") => postflop(&mut args[1..].to_vec()),
+        Some("browse") => browse(&args[1..]),
+        Some("narrow") => narrow(&mut args[1..].to_vec()),
+        _ => {
+            let villain_range = take_flag(&mut args, "--villain-range");
+            let empty = &String::new();
+            let community = args.first().unwrap_or(empty);
+            let hand_a = args.get(1).unwrap_or(empty);
+            let hand_b = args.get(2).unwrap_or(empty);
+            match villain_range {
+                Some(v) => solve_range(hand_a, &v, community),
+                None => solve(hand_a, hand_b, community),
+            }
+        }
+    }
+}
+
+/// Remove `name <value>` from `args`, returning the value.
+fn take_flag(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == name)?;
+    args.remove(i);
+    if i < args.len() {
+        Some(args.remove(i))
+    } else {
+        None
+    }
 }
 
 fn solve(hand_a: &str, hand_b: &str, community: &str) {
@@ -46,3 +81,433 @@ fn solve(hand_a: &str, hand_b: &str, community: &str) {
         }
     };
 }
+
+fn solve_range(hand: &str, villain: &str, community: &str) {
+    let range = match Range::from_arg(villain) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("{:^32}\n", e);
+            return;
+        }
+    };
+    print!(
+        "\n\
+        🎴 Community cards: {:>12}\n\
+        🎴 Your hand:       {:>12}\n\
+        🎴 Their range:     {:>12}\n\
+        \n\
+        Running numbers...",
+        prettify(community),
+        prettify(hand),
+        format!("{} combos", range.len()),
+    );
+    if stdout().flush().is_err() {
+        return;
+    }
+    let hero = Hand::from_string(hand).mask;
+    let community = Hand::from_string(community).mask;
+    match solve_ranges(hero, &[range], community, 0, 1) {
+        Ok(r) => {
+            let iterations = r.iterations;
+            let win = r.hero_win * 100.0;
+            let lose = r.hero_lose * 100.0;
+            let tie = r.hero_tie * 100.0;
+            println!(
+                "\r\
+                🔁 Outcomes:          {iterations:>10}\n\
+                👑 Win:               {win:>9.3}%\n\
+                💸 Lose:              {lose:>9.3}%\n\
+                🤝 Tie:               {tie:>9.3}%"
+            );
+        }
+        Err(e) => {
+            println!("\r{:^32}\n", e);
+        }
+    };
+}
+
+fn range(args: &[String]) {
+    let csv = args.iter().any(|a| a == "--csv");
+    let notation = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map(|s| s.as_str())
+        .unwrap_or("");
+    let range = match Range::from_arg(notation) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("{:^32}\n", e);
+            return;
+        }
+    };
+    let grid = range.to_grid();
+    if csv {
+        print!("{}", grid.to_csv());
+        return;
+    }
+    let combos = grid.combo_count();
+    let percent = combos / 1326.0 * 100.0;
+    println!(
+        "\n\
+        🎴 Range:   {notation}\n\
+        🧮 Combos:  {combos} ({percent:.1}%)\n"
+    );
+    print!("{}", grid.render());
+}
+
+fn dist(args: &mut Vec<String>) {
+    let buckets = take_flag(args, "--buckets")
+        .and_then(|b| b.parse().ok())
+        .unwrap_or(10);
+    let csv = args.iter().any(|a| a == "--csv");
+    args.retain(|a| a != "--csv");
+    let empty = &String::new();
+    let community = args.first().unwrap_or(empty);
+    let ranges = (
+        Range::from_arg(args.get(1).unwrap_or(empty)),
+        Range::from_arg(args.get(2).unwrap_or(empty)),
+    );
+    let (hero, villain) = match ranges {
+        (Ok(h), Ok(v)) => (h, v),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("{:^32}\n", e);
+            return;
+        }
+    };
+    let board = Hand::from_string(community).mask;
+    let (hero, villain) = match equity_distributions(&hero, &villain, board, 0, 1) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("{:^32}\n", e);
+            return;
+        }
+    };
+    if csv {
+        print!("{}", hero.to_csv());
+        return;
+    }
+    let show = |name: &str, d: &EquityDistribution| {
+        let equity = d.equity() * 100.0;
+        println!(
+            "\n🎴 {name}: {} combos, {equity:.2}% equity\n",
+            d.combos.len()
+        );
+        print!("{}", d.render(buckets, 40));
+    };
+    println!("\n🎴 Community cards: {:>12}", prettify(community));
+    show("Your range", &hero);
+    show("Their range", &villain);
+}
+
+fn tree(args: &[String]) {
+    let tree = match args
+        .join(" ")
+        .parse::<TreeConfig>()
+        .and_then(ActionTree::new)
+    {
+        Ok(t) => t,
+        Err(e) => {
+            println!("{:^32}\n", e);
+            return;
+        }
+    };
+    println!("\n🌳 {}\n", tree.config);
+    println!("{:<16}{:>12}", "Nodes", tree.len());
+    println!("{:<16}{:>12}", "Action nodes", tree.action_nodes());
+    println!("{:<16}{:>12}", "Chance nodes", tree.chance_nodes());
+    println!("{:<16}{:>12}", "Terminal nodes", tree.terminal_nodes());
+    println!("\nAction nodes per street");
+    for street in 0..tree.config.streets.len() {
+        let actions = tree
+            .nodes
+            .iter()
+            .filter(|n| n.street == street && matches!(n.kind, NodeKind::Action { .. }))
+            .count();
+        println!("{:<16}{:>12}", format!("Street {}", street + 1), actions);
+    }
+}
+
+fn pushfold(args: &mut Vec<String>) {
+    let mut number = |name: &str, default: f64| {
+        take_flag(args, name)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    let ante = number("--ante", 0.0);
+    let small_blind = number("--sb", 0.5);
+    let iterations = number("--iterations", 2000.0) as u32;
+    let samples = number("--samples", 4000.0) as u64;
+    let config = PushFold {
+        stack: args.first().and_then(|s| s.parse().ok()).unwrap_or(10.0),
+        small_blind,
+        big_blind: 1.0,
+        ante,
+    };
+    let solution =
+        match PreflopEquity::estimate(samples, 1).and_then(|e| config.solve(&e, iterations)) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("{:^32}\n", e);
+                return;
+            }
+        };
+    println!(
+        "\n🎴 {}bb stacks, blinds {}/1, ante {}\n",
+        config.stack, config.small_blind, config.ante
+    );
+    println!(
+        "Small blind jams: {}\n",
+        solution.push_range().export(RangeFormat::Weighted)
+    );
+    print!("{}", solution.push.render());
+    println!(
+        "\nBig blind calls: {}\n",
+        solution.call_range().export(RangeFormat::Weighted)
+    );
+    print!("{}", solution.call.render());
+    println!(
+        "\nExploitability after {} iterations: {:.4}bb per hand",
+        solution.iterations, solution.exploitability
+    );
+}
+
+fn parse_list(s: &str) -> Result<Vec<f64>, String> {
+    s.split(',')
+        .map(|x| x.trim().parse().map_err(|_| format!("bad number {:?}", x)))
+        .collect()
+}
+
+fn icm_cli(args: &mut Vec<String>) {
+    let hand = take_flag(args, "--hand");
+    let range = take_flag(args, "--range");
+    let posted = take_flag(args, "--posted");
+    let hero: usize = take_flag(args, "--hero")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let villain: usize = take_flag(args, "--villain")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1);
+    let run = || -> Result<(), String> {
+        let stacks = parse_list(args.first().ok_or("missing stacks")?)?;
+        let payouts = parse_list(args.get(1).ok_or("missing payouts")?)?;
+        let equities = icm(&stacks, &payouts)?;
+        println!("\n💰 {:>8}{:>14}{:>12}", "Player", "Stack", "$EV");
+        for (i, (s, e)) in stacks.iter().zip(&equities).enumerate() {
+            println!("   {:>8}{:>14}{:>12.2}", i, s, e);
+        }
+        let (Some(hand), Some(range)) = (hand, range) else {
+            return Ok(());
+        };
+        let posted = match posted {
+            Some(p) => parse_list(&p)?,
+            // the villain is all-in, nobody else has put chips in
+            None => (0..stacks.len())
+                .map(|i| if i == villain { stacks[i] } else { 0.0 })
+                .collect(),
+        };
+        let villains = [Range::from_arg(&range)?];
+        let equity = solve_equity(&Holdem, Hand::from_string(&hand).mask, &villains, 0, 0, 1)?;
+        let ev = all_in_ev(&stacks, &payouts, &posted, hero, villain, &equity)?;
+        println!(
+            "\n🎴 Player {} with {} facing player {}'s all-in",
+            hero,
+            prettify(&hand),
+            villain
+        );
+        println!("   {:>8}{:>14}{:>12}", "", "Chips", "$EV");
+        println!("   {:>8}{:>14.1}{:>12.2}", "Fold", ev.fold_chips, ev.fold);
+        println!("   {:>8}{:>14.1}{:>12.2}", "Call", ev.call_chips, ev.call);
+        println!("\n{}", if ev.should_call() { "Call" } else { "Fold" });
+        Ok(())
+    };
+    if let Err(e) = run() {
+        println!("{:^32}\n", e);
+    }
+}
+
+fn shove(args: &mut Vec<String>) {
+    let board = take_flag(args, "--board").unwrap_or_default();
+    let pot = take_flag(args, "--pot");
+    let to_call = take_flag(args, "--call");
+    let run = || -> Result<(), String> {
+        let hand = args.first().ok_or("missing hand")?;
+        let villain = Range::from_arg(args.get(1).ok_or("missing villain range")?)?;
+        let amount = |v: Option<String>, name: &str| -> Result<f64, String> {
+            v.ok_or(format!("missing {}", name))?
+                .parse()
+                .map_err(|_| format!("bad {}", name))
+        };
+        let spot = ShoveSpot {
+            hero: Hand::from_string(hand).mask,
+            community: Hand::from_string(&board).mask,
+            pot: amount(pot, "--pot")?,
+            to_call: amount(to_call, "--call")?,
+        };
+        let ranking = class_ranking(spot.community, 2000, 1)?;
+        let d = spot.decide(&villain, &ranking, 0, 1)?;
+        println!("\n🎴 Your hand: {:>12}", prettify(hand));
+        if !board.is_empty() {
+            println!("🎴 Community cards: {:>12}", prettify(&board));
+        }
+        println!("\n{:<22}{:>10.2}%", "Equity", d.equity * 100.0);
+        println!(
+            "{:<22}{:>10.2}%",
+            "Required by pot odds",
+            d.required * 100.0
+        );
+        println!("{:<22}{:>11.2}", "Call EV (chips)", d.call_ev);
+        match d.break_even_width {
+            Some(w) => println!("{:<22}{:>10.2}%", "Break-even range", w * 100.0),
+            None => println!("{:<22}{:>11}", "Break-even range", "none"),
+        }
+        println!("\n{}", if d.should_call() { "Call" } else { "Fold" });
+        Ok(())
+    };
+    if let Err(e) = run() {
+        println!("{:^32}\n", e);
+    }
+}
+
+fn postflop(args: &mut Vec<String>) {
+    let out = take_flag(args, "--out");
+    let iterations: u32 = take_flag(args, "--iterations")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1000);
+    let target: f64 = take_flag(args, "--target")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.0);
+    let big_blind: f64 = take_flag(args, "--bb")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1.0);
+    let run = || -> Result<(), String> {
+        let board = Hand::from_string(args.first().ok_or("missing board")?).mask;
+        let oop = Range::from_arg(args.get(1).ok_or("missing OOP range")?)?;
+        let ip = Range::from_arg(args.get(2).ok_or("missing IP range")?)?;
+        let mut config: TreeConfig = args[3..].join(" ").parse()?;
+        // one street of sizes stands for every street left
+        let streets = 6usize.saturating_sub(board.count_ones() as usize);
+        if config.streets.len() == 1 && streets > 1 {
+            config.streets = vec![config.streets[0].clone(); streets];
+        }
+        let mut solver = CfrSolver::new(board, [&oop, &ip], config)?;
+        println!(
+            "\n🎴 Board {}, {} runouts, {:.1} MB\n",
+            prettify(&args[0]),
+            solver.runouts(),
+            solver.memory_usage() as f64 / 1e6
+        );
+        let check_every = (iterations / 10).max(1);
+        solver.solve_until(iterations, target, check_every, |i, e| {
+            println!(
+                "{:>8} iterations {:>12.4} chips {:>10.1} mbb",
+                i,
+                e,
+                e / big_blind * 1000.0
+            );
+        });
+        if let Some(path) = &out {
+            StrategyFile::from_solver(&solver).save(path)?;
+            println!("\nSaved to {}", path);
+        }
+        Ok(())
+    };
+    if let Err(e) = run() {
+        println!("{:^32}\n", e);
+    }
+}
+
+fn browse(args: &[String]) {
+    let run = || -> Result<(), String> {
+        let file = StrategyFile::load(args.first().ok_or("missing strategy file")?)?;
+        let path = args[1..].join(" ");
+        let (node, dealt) = file.navigate(&path)?;
+        let mut board = Hand { mask: file.board }.to_string();
+        for c in &dealt {
+            board.push_str(&c.to_string());
+        }
+        println!("\n🎴 Board {}", prettify(&board));
+        println!(
+            "🌳 {} after {} iterations, exploitability {:.3} chips",
+            file.tree.config, file.iterations, file.exploitability
+        );
+        println!("📍 {}\n", if path.is_empty() { "root" } else { &path });
+        match file.tree.nodes[node].kind {
+            NodeKind::Action { .. } => {}
+            NodeKind::Chance { .. } => {
+                println!("Deal a card next, e.g. \"{} > Ah\"", path);
+                return Ok(());
+            }
+            _ => {
+                println!("The hand is over");
+                return Ok(());
+            }
+        }
+        let s = file.strategy(node, &dealt)?;
+        println!(
+            "{} to act, pot {}, range worth {:.2}\n",
+            if s.player == 0 { "OOP" } else { "IP" },
+            file.tree.pot(node),
+            s.value()
+        );
+        for (a, label) in s.labels.iter().enumerate() {
+            println!("{} {:.1}%\n", label, s.frequency(a) * 100.0);
+            print!("{}", s.grid(a).render());
+            println!();
+        }
+        Ok(())
+    };
+    if let Err(e) = run() {
+        println!("{:^32}\n", e);
+    }
+}
+
+fn narrow(args: &mut Vec<String>) {
+    let file = take_flag(args, "--file");
+    let player = take_flag(args, "--player");
+    let run = || -> Result<(), String> {
+        let show = |label: &str, before: &Range, after: &Range| {
+            let kept = after.combo_count(0) / before.combo_count(0) * 100.0;
+            println!(
+                "{:<40}{:>10.1} combos {:>7.1}% kept",
+                label,
+                after.combo_count(0),
+                kept
+            );
+        };
+        let mut range;
+        if let Some(path) = file {
+            let file = StrategyFile::load(path)?;
+            let player = match player.as_deref() {
+                Some("oop") | None => 0,
+                Some("ip") => 1,
+                Some(p) => return Err(format!("player must be oop or ip, got {}", p)),
+            };
+            let prior = match args.len() {
+                2.. => Range::from_arg(&args[0])?,
+                _ => file.ranges[player].clone(),
+            };
+            let line = args.last().cloned().unwrap_or_default();
+            range = prior.narrow_along(&file, player, &line)?;
+            println!();
+            show(&line, &prior, &range);
+        } else {
+            range = Range::from_arg(args.first().ok_or("missing range")?)?;
+            println!();
+            for step in args[1..].chunks(2) {
+                let [board, rule] = step else {
+                    return Err("each board needs a rule".into());
+                };
+                let rule: ActionRule = rule.parse()?;
+                let next = range.narrow_by_rule(&rule, Hand::from_string(board).mask)?;
+                show(&format!("{} {}", prettify(board), step[1]), &range, &next);
+                range = next;
+            }
+        }
+        println!("\nRange: {}\n", range.export(RangeFormat::Weighted));
+        print!("{}", range.to_grid().render());
+        Ok(())
+    };
+    if let Err(e) = run() {
+        println!("{:^32}\n", e);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::poker::evaluator::{category, eval, pack_suit, straight_top, CATEGORY_NAMES};
+use crate::poker::grid::{class_label, locate, GRID_SIZE};
+use crate::poker::Range;
+
+/// Weighted combos of one group before and after removing hero's cards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockerCount {
+    pub label: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+impl BlockerCount {
+    pub fn removed(&self) -> f64 {
+        self.before - self.after
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BlockerReport {
+    pub total_before: f64,
+    pub total_after: f64,
+    /// Per 13x13 class, in grid order, classes live on the board only.
+    pub classes: Vec<BlockerCount>,
+    /// Per made hand category on the board, then draws and the nuts.
+    pub categories: Vec<BlockerCount>,
+}
+
+impl BlockerReport {
+    pub fn class(&self, label: &str) -> Option<&BlockerCount> {
+        self.classes.iter().find(|c| c.label == label)
+    }
+
+    pub fn category(&self, label: &str) -> Option<&BlockerCount> {
+        self.categories.iter().find(|c| c.label == label)
+    }
+}
+
+pub const FLUSH_DRAW: &str = "Flush draw";
+pub const STRAIGHT_DRAW: &str = "Straight draw";
+pub const NUTS: &str = "Nuts";
+
+fn rank_mask(mask: u64) -> u16 {
+    (0..4).fold(0, |acc, s| acc | pack_suit(mask, s))
+}
+
+fn straight_outs(rmask: u16) -> u32 {
+    (0..13)
+        .filter(|&r| rmask & (1 << r) == 0 && straight_top(rmask | (1 << r)) >= 0)
+        .count() as u32
+}
+
+pub(crate) fn has_flush_draw(combo: u64, board: u64) -> bool {
+    (0..4).any(|s| pack_suit(combo | board, s).count_ones() == 4 && pack_suit(combo, s) != 0)
+}
+
+pub(crate) fn has_straight_draw(combo: u64, board: u64) -> bool {
+    let rmask = rank_mask(combo | board);
+    straight_top(rmask) < 0 && straight_outs(rmask) > straight_outs(rank_mask(board))
+}
+
+/// Board-aware groups a combo belongs to: its made hand category, plus draws
+/// while cards are to come.
+fn groups(combo: u64, board: u64) -> Vec<&'static str> {
+    let score = eval(combo | board);
+    let cat = category(score);
+    let mut out = vec![CATEGORY_NAMES[cat]];
+    if board.count_ones() < 5 {
+        if cat < 5 && has_flush_draw(combo, board) {
+            out.push(FLUSH_DRAW);
+        }
+        if cat < 4 && has_straight_draw(combo, board) {
+            out.push(STRAIGHT_DRAW);
+        }
+    }
+    out
+}
+
+/// Score of the best hand two cards off `board` make with it.
+fn nut_score(board: u64) -> u32 {
+    let mut best = 0;
+    for i in 0..52 {
+        for j in i + 1..52 {
+            let hole = (1u64 << i) | (1u64 << j);
+            if hole & board == 0 {
+                best = best.max(eval(hole | board));
+            }
+        }
+    }
+    best
+}
+
+impl Range {
+    /// Weighted number of combos not touching `dead`.
+    pub fn combo_count(&self, dead: u64) -> f64 {
+        self.live_combos(dead).map(|c| c.weight as f64).sum()
+    }
+
+    /// How hero's cards change this range on `board`. "Before" counts combos
+    /// live on the board alone, "after" also removes combos touching `hero`.
+    /// The nuts are the best hand any two cards make on the board, counted
+    /// where the range holds it.
+    pub fn blockers(&self, board: u64, hero: u64) -> BlockerReport {
+        let mut before = [[0.0f64; GRID_SIZE]; GRID_SIZE];
+        let mut after = [[0.0f64; GRID_SIZE]; GRID_SIZE];
+        let mut categories: Vec<BlockerCount> = CATEGORY_NAMES
+            .iter()
+            .chain([FLUSH_DRAW, STRAIGHT_DRAW].iter())
+            .map(|&label| BlockerCount {
+                label: label.to_string(),
+                before: 0.0,
+                after: 0.0,
+            })
+            .collect();
+        let board_made = board.count_ones() >= 3;
+        let mut report = BlockerReport::default();
+        let mut nuts = BlockerCount {
+            label: NUTS.to_string(),
+            before: 0.0,
+            after: 0.0,
+        };
+        let best = if board_made { nut_score(board) } else { 0 };
+        for c in self.live_combos(board) {
+            let w = c.weight as f64;
+            let blocked = c.mask & hero != 0;
+            report.total_before += w;
+            if !blocked {
+                report.total_after += w;
+            }
+            if let Some((row, col, _)) = locate(c.mask) {
+                before[row][col] += w;
+                if !blocked {
+                    after[row][col] += w;
+                }
+            }
+            if !board_made {
+                continue;
+            }
+            for label in groups(c.mask, board) {
+                let g = categories.iter_mut().find(|g| g.label == label).unwrap();
+                g.before += w;
+                if !blocked {
+                    g.after += w;
+                }
+            }
+            if eval(c.mask | board) == best {
+                nuts.before += w;
+                if !blocked {
+                    nuts.after += w;
+                }
+            }
+        }
+        for row in 0..GRID_SIZE {
+            for col in 0..GRID_SIZE {
+                if before[row][col] > 0.0 {
+                    report.classes.push(BlockerCount {
+                        label: class_label(row, col),
+                        before: before[row][col],
+                        after: after[row][col],
+                    });
+                }
+            }
+        }
+        if board_made {
+            categories.retain(|g| g.before > 0.0);
+            categories.push(nuts);
+            report.categories = categories;
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    #[test]
+    fn combo_count_weighted() {
+        let r = Range::parse("AA,KK:0.5").unwrap();
+        assert_eq!(r.combo_count(0), 9.0);
+        assert_eq!(r.combo_count(Hand::from_string("As").mask), 6.0);
+    }
+
+    #[test]
+    fn class_removal() {
+        let r = Range::from_notation("AA,AKs,QQ").unwrap();
+        let board = Hand::from_string("2c7d9h").mask;
+        let hero = Hand::from_string("AhTd").mask;
+        let report = r.blockers(board, hero);
+        assert_eq!(report.total_before, 16.0);
+        assert_eq!(report.total_after, 16.0 - 3.0 - 1.0);
+        let aa = report.class("AA").unwrap();
+        assert_eq!((aa.before, aa.after), (6.0, 3.0));
+        assert_eq!(report.class("AKs").unwrap().removed(), 1.0);
+        assert_eq!(report.class("QQ").unwrap().removed(), 0.0);
+    }
+
+    #[test]
+    fn nut_flush_blocker() {
+        let r = Range::from_notation("AhKh,AhQh,KhQh,QQ").unwrap();
+        let board = Hand::from_string("2h7h9hTc").mask;
+        let report = r.blockers(board, Hand::from_string("AhJc").mask);
+        let nuts = report.category(NUTS).unwrap();
+        assert_eq!((nuts.before, nuts.after), (1.0, 0.0));
+        let flush = report.category("Flush").unwrap();
+        assert_eq!((flush.before, flush.after), (3.0, 1.0));
+    }
+
+    #[test]
+    fn nuts_are_the_boards() {
+        let board = Hand::from_string("2c7d9h").mask;
+        let overpair = Range::from_notation("QQ").unwrap().blockers(board, 0);
+        assert_eq!(overpair.category(NUTS).unwrap().before, 0.0);
+        let sets = Range::from_notation("QQ,99").unwrap();
+        let report = sets.blockers(board, Hand::from_string("9c").mask);
+        let nuts = report.category(NUTS).unwrap();
+        assert_eq!((nuts.before, nuts.after), (3.0, 1.0));
+    }
+
+    #[test]
+    fn draws_on_flop() {
+        let r = Range::from_notation("AhKh,JsTs").unwrap();
+        let board = Hand::from_string("2h7h9c").mask;
+        let report = r.blockers(board, 0);
+        assert_eq!(report.category(FLUSH_DRAW).unwrap().before, 1.0);
+        assert_eq!(report.category(STRAIGHT_DRAW).unwrap().before, 1.0);
+    }
+
+    #[test]
+    fn preflop_has_no_categories() {
+        let r = Range::from_notation("AA").unwrap();
+        let report = r.blockers(0, Hand::from_string("As").mask);
+        assert!(report.categories.is_empty());
+        assert_eq!(report.total_after, 3.0);
+    }
+}
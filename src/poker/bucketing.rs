@@ -0,0 +1,523 @@
+use crate::poker::deck::XorShift64;
+use crate::poker::evaluator::eval;
+use crate::poker::game::pick_random_board;
+use crate::poker::indexer::HandIndexer;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"PSBT";
+const VERSION: u8 = 1;
+
+/// How hands are grouped into buckets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BucketMethod {
+    /// Buckets of about equal size by expected hand strength.
+    Ehs,
+    /// k-means on (EHS, EHS²).
+    KMeans,
+    /// k-means on histograms of rollout equity, `bins` wide, with earth
+    /// mover's distance.
+    Emd { bins: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketConfig {
+    pub buckets: usize,
+    pub method: BucketMethod,
+    /// Board completions sampled per hand.
+    pub rollouts: u32,
+    /// Random opponent hands faced per rollout.
+    pub opponents: u32,
+    /// Hands the buckets are fitted on; the rest of the street is only
+    /// assigned to them.
+    pub sample: usize,
+    pub seed: u64,
+}
+
+impl Default for BucketConfig {
+    fn default() -> Self {
+        Self {
+            buckets: 50,
+            method: BucketMethod::Emd { bins: 20 },
+            rollouts: 64,
+            opponents: 16,
+            sample: 20_000,
+            seed: 1,
+        }
+    }
+}
+
+/// Bucket of every suit-isomorphic (hole, board) pair of one street,
+/// addressed by a `HandIndexer` over hole and board rounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketTable {
+    pub board_cards: u32,
+    pub buckets: u16,
+    indexer: HandIndexer,
+    entries: Vec<u16>,
+}
+
+/// Hole cards then the whole board as one round.
+fn street_indexer(board_cards: u32) -> Result<HandIndexer, String> {
+    match board_cards {
+        0 => HandIndexer::new(&[2]),
+        3..=5 => HandIndexer::new(&[2, board_cards]),
+        _ => Err("board must have 0, 3, 4 or 5 cards".into()),
+    }
+}
+
+fn street_rounds(hole: u64, board: u64) -> Vec<u64> {
+    if board == 0 {
+        vec![hole]
+    } else {
+        vec![hole, board]
+    }
+}
+
+impl BucketTable {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Bucket of any (hole, board) pair of this street.
+    pub fn bucket(&self, hole: u64, board: u64) -> Option<u16> {
+        if board.count_ones() != self.board_cards {
+            return None;
+        }
+        let i = self.indexer.index(&street_rounds(hole, board)).ok()?;
+        Some(self.entries[i as usize])
+    }
+
+    /// Number of canonical hands per bucket.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut out = vec![0; self.buckets as usize];
+        for &b in &self.entries {
+            out[b as usize] += 1;
+        }
+        out
+    }
+
+    /// Little endian binary layout: magic, version, board cards, bucket
+    /// count, entry count, then one bucket per hand index.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(16 + self.entries.len() * 2);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(self.board_cards as u8);
+        out.extend_from_slice(&self.buckets.to_le_bytes());
+        out.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for &bucket in &self.entries {
+            out.extend_from_slice(&bucket.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 16 || &bytes[..4] != MAGIC {
+            return Err("not a bucket table".into());
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("unsupported bucket table version {}", bytes[4]));
+        }
+        let board_cards = bytes[5] as u32;
+        let indexer = street_indexer(board_cards)?;
+        let buckets = u16::from_le_bytes([bytes[6], bytes[7]]);
+        let count = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        if count != indexer.size(indexer.rounds() - 1) {
+            return Err("bucket table does not cover the street".into());
+        }
+        let body = &bytes[16..];
+        if body.len() as u64 != count * 2 {
+            return Err("truncated bucket table".into());
+        }
+        let mut entries = Vec::with_capacity(count as usize);
+        for e in body.chunks_exact(2) {
+            let bucket = u16::from_le_bytes([e[0], e[1]]);
+            if bucket >= buckets {
+                return Err(format!("bucket {} out of range", bucket));
+            }
+            entries.push(bucket);
+        }
+        Ok(Self {
+            board_cards,
+            buckets,
+            indexer,
+            entries,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes())
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Equity of each sampled rollout against random opponent hands.
+fn rollout_equities(
+    hole: u64,
+    board: u64,
+    config: &BucketConfig,
+    rng: &mut XorShift64,
+) -> Vec<f64> {
+    let need = 5 - board.count_ones();
+    let rollouts = if need == 0 { 1 } else { config.rollouts.max(1) };
+    let opponents = config.opponents.max(1);
+    (0..rollouts)
+        .map(|_| {
+            let full = board | pick_random_board(hole | board, need, rng);
+            let hero = eval(hole | full);
+            let mut won = 0.0;
+            for _ in 0..opponents {
+                let villain = eval(pick_random_board(hole | full, 2, rng) | full);
+                won += match hero.cmp(&villain) {
+                    std::cmp::Ordering::Greater => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Less => 0.0,
+                };
+            }
+            won / opponents as f64
+        })
+        .collect()
+}
+
+fn features(equities: &[f64], method: BucketMethod) -> Vec<f64> {
+    let n = equities.len() as f64;
+    match method {
+        BucketMethod::Ehs => vec![equities.iter().sum::<f64>() / n],
+        BucketMethod::KMeans => vec![
+            equities.iter().sum::<f64>() / n,
+            equities.iter().map(|e| e * e).sum::<f64>() / n,
+        ],
+        BucketMethod::Emd { bins } => {
+            let bins = bins.max(1);
+            let mut hist = vec![0.0; bins];
+            for &e in equities {
+                hist[((e * bins as f64) as usize).min(bins - 1)] += 1.0 / n;
+            }
+            hist
+        }
+    }
+}
+
+/// Earth mover's distance between two histograms over the same bins: the
+/// area between their cumulative sums.
+pub fn emd(a: &[f64], b: &[f64]) -> f64 {
+    let mut carried = 0.0;
+    let mut out = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        carried += x - y;
+        out += carried.abs();
+    }
+    out
+}
+
+fn l2(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Fit at most `k` centers to `points` with k-means++ seeding and Lloyd
+/// iterations under `distance`, ordered by equity so bucket 0 is the
+/// weakest.
+pub(crate) fn kmeans(
+    points: &[Vec<f64>],
+    k: usize,
+    distance: fn(&[f64], &[f64]) -> f64,
+    seed: u64,
+) -> Vec<Vec<f64>> {
+    let k = k.clamp(1, points.len().max(1));
+    let mut rng = XorShift64::new(seed);
+    let mut centers: Vec<Vec<f64>> = vec![points[rng.range(points.len())].clone()];
+    let mut closest: Vec<f64> = points.iter().map(|p| distance(p, &centers[0])).collect();
+    while centers.len() < k {
+        let total: f64 = closest.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = rng.next_f64() * total;
+        let mut pick = points.len() - 1;
+        for (i, d) in closest.iter().enumerate() {
+            if target < *d {
+                pick = i;
+                break;
+            }
+            target -= d;
+        }
+        centers.push(points[pick].clone());
+        for (n, p) in closest.iter_mut().zip(points) {
+            *n = n.min(distance(p, &points[pick]));
+        }
+    }
+
+    let mut assign = vec![0usize; points.len()];
+    for round in 0..100 {
+        let mut moved = false;
+        for (a, p) in assign.iter_mut().zip(points) {
+            let best = nearest(p, &centers, distance) as usize;
+            if round == 0 || best != *a {
+                moved = true;
+                *a = best;
+            }
+        }
+        if !moved {
+            break;
+        }
+        let dims = centers[0].len();
+        let mut sums = vec![vec![0.0; dims]; centers.len()];
+        let mut counts = vec![0usize; centers.len()];
+        for (&a, p) in assign.iter().zip(points) {
+            counts[a] += 1;
+            for (s, x) in sums[a].iter_mut().zip(p) {
+                *s += x;
+            }
+        }
+        for ((c, s), &n) in centers.iter_mut().zip(sums).zip(&counts) {
+            if n > 0 {
+                *c = s.into_iter().map(|x| x / n as f64).collect();
+            }
+        }
+    }
+
+    centers.sort_by(|a, b| strength(a).total_cmp(&strength(b)));
+    centers
+}
+
+/// Index of the center closest to `point`.
+fn nearest(point: &[f64], centers: &[Vec<f64>], distance: fn(&[f64], &[f64]) -> f64) -> u16 {
+    (0..centers.len())
+        .min_by(|&x, &y| distance(point, &centers[x]).total_cmp(&distance(point, &centers[y])))
+        .unwrap() as u16
+}
+
+/// Mean equity of a feature vector: the first feature, or the histogram's
+/// mean for more than two features.
+fn strength(f: &[f64]) -> f64 {
+    if f.len() <= 2 {
+        return f[0];
+    }
+    let bins = f.len() as f64;
+    f.iter()
+        .enumerate()
+        .map(|(i, p)| p * (i as f64 + 0.5) / bins)
+        .sum()
+}
+
+/// Buckets fitted on a sample of hands.
+enum Fit {
+    /// Lowest EHS of every bucket but the first.
+    Bounds(Vec<f64>),
+    Centers(Vec<Vec<f64>>, fn(&[f64], &[f64]) -> f64),
+}
+
+impl Fit {
+    fn bucket(&self, features: &[f64]) -> u16 {
+        match self {
+            Fit::Bounds(bounds) => bounds.partition_point(|&b| b <= features[0]) as u16,
+            Fit::Centers(centers, distance) => nearest(features, centers, *distance),
+        }
+    }
+}
+
+/// Features of the canonical hand at `index` of the last round. Rollouts
+/// are seeded from the index and `config.seed`, so a hand always gets the
+/// same features.
+fn hand_features(indexer: &HandIndexer, index: u64, config: &BucketConfig) -> Vec<f64> {
+    let rounds = indexer.unindex(indexer.rounds() - 1, index).unwrap();
+    let (hole, board) = (rounds[0], rounds.get(1).copied().unwrap_or(0));
+    let seed = config.seed ^ (index + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut rng = XorShift64::new(seed);
+    features(
+        &rollout_equities(hole, board, config, &mut rng),
+        config.method,
+    )
+}
+
+/// Buckets fitted on `config.sample` random hands of the indexer's last
+/// round, or on every hand when there are fewer.
+fn fit_buckets(indexer: &HandIndexer, config: &BucketConfig) -> Fit {
+    let size = indexer.size(indexer.rounds() - 1);
+    let sample: Vec<u64> = if size <= config.sample as u64 {
+        (0..size).collect()
+    } else {
+        let mut rng = XorShift64::new(config.seed);
+        let mut picked: Vec<u64> = (0..config.sample).map(|_| rng.next_u64() % size).collect();
+        picked.sort_unstable();
+        picked.dedup();
+        picked
+    };
+    let points: Vec<Vec<f64>> = sample
+        .iter()
+        .map(|&i| hand_features(indexer, i, config))
+        .collect();
+
+    match config.method {
+        BucketMethod::Ehs => {
+            let mut ehs: Vec<f64> = points.iter().map(|p| p[0]).collect();
+            ehs.sort_by(f64::total_cmp);
+            let mut bounds: Vec<f64> = (1..config.buckets)
+                .map(|b| ehs[b * ehs.len() / config.buckets])
+                .collect();
+            bounds.dedup();
+            Fit::Bounds(bounds)
+        }
+        BucketMethod::KMeans => Fit::Centers(kmeans(&points, config.buckets, l2, config.seed), l2),
+        BucketMethod::Emd { .. } => {
+            Fit::Centers(kmeans(&points, config.buckets, emd, config.seed), emd)
+        }
+    }
+}
+
+/// Bucket every suit-isomorphic hand of the street with `board_cards`
+/// community cards (0, 3, 4 or 5). Buckets are fitted on `config.sample`
+/// random hands, then a second pass assigns every hand to one without
+/// keeping its features, so memory is the table itself: 2 bytes per hand.
+/// The same config always gives the same table.
+pub fn build_buckets(board_cards: u32, config: &BucketConfig) -> Result<BucketTable, String> {
+    let indexer = street_indexer(board_cards)?;
+    if config.buckets == 0 || config.buckets > u16::MAX as usize {
+        return Err(format!("bucket count must be 1-{}", u16::MAX));
+    }
+    if config.sample == 0 {
+        return Err("sample must hold at least one hand".into());
+    }
+    let fit = fit_buckets(&indexer, config);
+    let size = indexer.size(indexer.rounds() - 1);
+    let entries: Vec<u16> = (0..size)
+        .map(|i| fit.bucket(&hand_features(&indexer, i, config)))
+        .collect();
+    let buckets = entries.iter().max().map_or(0, |&b| b + 1);
+    Ok(BucketTable {
+        board_cards,
+        buckets,
+        indexer,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    fn small(method: BucketMethod) -> BucketConfig {
+        BucketConfig {
+            buckets: 8,
+            method,
+            rollouts: 24,
+            opponents: 8,
+            sample: 500,
+            seed: 5,
+        }
+    }
+
+    #[test]
+    fn emd_of_histograms() {
+        assert_eq!(emd(&[1.0, 0.0, 0.0], &[0.0, 0.0, 1.0]), 2.0);
+        assert_eq!(emd(&[0.5, 0.5], &[0.5, 0.5]), 0.0);
+        assert!((emd(&[1.0, 0.0], &[0.5, 0.5]) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn kmeans_separates_clusters() {
+        let points: Vec<Vec<f64>> = [0.1, 0.12, 0.11, 0.9, 0.88, 0.5, 0.52]
+            .iter()
+            .map(|&x| vec![x])
+            .collect();
+        let centers = kmeans(&points, 3, l2, 1);
+        let assign: Vec<u16> = points.iter().map(|p| nearest(p, &centers, l2)).collect();
+        assert_eq!(&assign[..3], &[0, 0, 0]);
+        assert_eq!(&assign[3..5], &[2, 2]);
+        assert_eq!(&assign[5..], &[1, 1]);
+    }
+
+    #[test]
+    fn preflop_buckets_are_reproducible() {
+        for method in [
+            BucketMethod::Ehs,
+            BucketMethod::KMeans,
+            BucketMethod::Emd { bins: 10 },
+        ] {
+            let table = build_buckets(0, &small(method)).unwrap();
+            assert_eq!(table.len(), 169);
+            assert_eq!(table, build_buckets(0, &small(method)).unwrap());
+            assert_eq!(table.sizes().iter().sum::<usize>(), 169);
+            let aces = table.bucket(mask("AsAh"), 0).unwrap();
+            assert_eq!(table.bucket(mask("AdAc"), 0), Some(aces));
+            assert_eq!(aces + 1, table.buckets);
+            let trash = table.bucket(mask("7d2c"), 0).unwrap();
+            assert!(trash < aces);
+        }
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        let table = build_buckets(0, &small(BucketMethod::Ehs)).unwrap();
+        let bytes = table.to_bytes();
+        assert_eq!(BucketTable::from_bytes(&bytes).unwrap(), table);
+        let path = std::env::temp_dir().join("poker-solver-buckets-test.bin");
+        table.save(&path).unwrap();
+        assert_eq!(BucketTable::load(&path).unwrap(), table);
+        let _ = fs::remove_file(&path);
+        assert!(BucketTable::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(BucketTable::from_bytes(b"nope").is_err());
+        let mut newer = bytes.clone();
+        newer[4] = VERSION + 1;
+        assert!(BucketTable::from_bytes(&newer).is_err());
+    }
+
+    #[test]
+    fn flop_sample_orders_hands() {
+        let config = BucketConfig {
+            sample: 400,
+            ..small(BucketMethod::Emd { bins: 8 })
+        };
+        let indexer = street_indexer(3).unwrap();
+        let fit = fit_buckets(&indexer, &config);
+        let bucket = |hole: &str, board: &str| {
+            let i = indexer.index(&[mask(hole), mask(board)]).unwrap();
+            fit.bucket(&hand_features(&indexer, i, &config))
+        };
+        let set = bucket("9s9d", "2c7d9h");
+        assert_eq!(bucket("9c9h", "2s7h9d"), set);
+        assert!(bucket("3s4d", "KcQdJh") < set);
+        assert!(bucket("AsKd", "AcKh2d") <= set);
+    }
+
+    /// Buckets all 1.3M flop hands, slow without optimizations.
+    #[test]
+    #[ignore]
+    fn flop_table_covers_the_street() {
+        let config = BucketConfig {
+            rollouts: 2,
+            opponents: 2,
+            sample: 300,
+            ..small(BucketMethod::Ehs)
+        };
+        let table = build_buckets(3, &config).unwrap();
+        assert_eq!(table.len(), 1_286_792);
+        assert_eq!(table.sizes().iter().sum::<usize>(), table.len());
+        let set = table.bucket(mask("9s9d"), mask("2c7d9h")).unwrap();
+        assert_eq!(table.bucket(mask("9c9h"), mask("2s7h9d")), Some(set));
+        assert!(table.bucket(mask("3s4d"), mask("KcQdJh")).unwrap() < set);
+        assert_eq!(table.bucket(mask("3s4d"), mask("KcQd")), None);
+    }
+
+    #[test]
+    fn rejects_bad_street() {
+        assert!(build_buckets(2, &BucketConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::poker::hand::SUIT_COUNT;
+use crate::poker::range::{RANKS, SUITS};
+use std::fmt;
+use std::str::FromStr;
+
+pub const RANK_COUNT: u8 = 13;
+
+/// A single card, stored as its bit index `rank * 4 + suit` in a `Hand` mask.
+/// Ranks run 0 (deuce) to 12 (ace), suits follow `scdh`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Card(u8);
+
+impl Card {
+    /// Errors on a rank over 12 or a suit over 3.
+    pub fn new(rank: u8, suit: u8) -> Result<Self, String> {
+        if rank >= RANK_COUNT || suit >= SUIT_COUNT as u8 {
+            return Err(format!("no card of rank {} and suit {}", rank, suit));
+        }
+        Ok(Self(rank * SUIT_COUNT as u8 + suit))
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        if index < RANK_COUNT * SUIT_COUNT as u8 {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    pub fn rank(self) -> u8 {
+        self.0 / SUIT_COUNT as u8
+    }
+
+    pub fn suit(self) -> u8 {
+        self.0 % SUIT_COUNT as u8
+    }
+
+    pub fn mask(self) -> u64 {
+        1u64 << self.0
+    }
+
+    pub fn rank_char(self) -> char {
+        RANKS[self.rank() as usize] as char
+    }
+
+    pub fn suit_char(self) -> char {
+        SUITS[self.suit() as usize] as char
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.rank_char(), self.suit_char())
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.trim().as_bytes();
+        if bytes.len() != 2 {
+            return Err(format!("bad card: {}", s));
+        }
+        let rank = RANKS
+            .iter()
+            .position(|&c| c == bytes[0])
+            .ok_or_else(|| format!("bad rank: {}", bytes[0] as char))?;
+        let suit = SUITS
+            .iter()
+            .position(|&c| c == bytes[1])
+            .ok_or_else(|| format!("bad suit: {}", bytes[1] as char))?;
+        Self::new(rank as u8, suit as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let c: Card = "Qh".parse().unwrap();
+        assert_eq!(c.rank(), 10);
+        assert_eq!(c.suit(), 3);
+        assert_eq!(c.index(), 43);
+        assert_eq!(c.mask(), 1u64 << 43);
+        assert_eq!(c, Card::new(10, 3).unwrap());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for i in 0..52 {
+            let c = Card::from_index(i).unwrap();
+            assert_eq!(c.to_string().parse::<Card>().unwrap(), c);
+        }
+        assert!(Card::from_index(52).is_none());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("1s".parse::<Card>().is_err());
+        assert!("Ax".parse::<Card>().is_err());
+        assert!("AsK".parse::<Card>().is_err());
+    }
+
+    #[test]
+    fn new_rejects_bad_rank() {
+        assert_eq!(
+            Card::new(13, 0).unwrap_err(),
+            "no card of rank 13 and suit 0"
+        );
+        assert!(Card::new(0, 4).is_err());
+    }
+}
@@ -0,0 +1,1454 @@
+use std::collections::HashMap;
+
+use crate::poker::card::Card;
+use crate::poker::distribution::{Ranking, Showdown};
+use crate::poker::evaluator::pack_suit;
+use crate::poker::hand::{Hand, SUIT_COUNT};
+use crate::poker::isomorphism::unpack_suit;
+use crate::poker::range::Combo;
+use crate::poker::tree::{ActionTree, NodeKind, TreeConfig};
+use crate::poker::Range;
+
+/// Regret update rule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CfrAlgorithm {
+    /// CFR+: regrets floored at zero, linearly weighted average strategy.
+    CfrPlus,
+    /// Discounted CFR with alpha 1.5, beta 0 and gamma 2.
+    Discounted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Both players follow the average strategy.
+    Average,
+    /// The traverser best responds to the opponent's average strategy.
+    BestResponse,
+}
+
+/// Suit transposition leaving both ranges unchanged, with the combo each
+/// combo is mapped to, per player.
+#[derive(Debug, Clone)]
+struct Swap {
+    suits: (u32, u32),
+    combo: [Vec<usize>; 2],
+}
+
+/// A card dealt at a chance node.
+#[derive(Debug, Clone, Copy)]
+struct Deal {
+    card: u32,
+    /// Runout reached; suit isomorphic cards share one.
+    runout: usize,
+    /// Swap taking the canonical card `next[from]` onto this one.
+    swap: Option<usize>,
+    from: usize,
+}
+
+/// Board reached once some of the subgame's cards are dealt.
+#[derive(Debug, Clone)]
+struct Runout {
+    board: u64,
+    /// Position among the runouts of the same street.
+    slot: usize,
+    next: Vec<Deal>,
+    /// Both players' rankings once the board is complete.
+    ranking: Option<[Ranking; 2]>,
+}
+
+/// Best response of one player to the other's fixed average strategy.
+/// `CfrSolver::response_spots` lists the actions it takes.
+#[derive(Debug, Clone)]
+pub struct BestResponse {
+    pub player: usize,
+    /// Chips each combo expects to win from the pot, in `combos` order.
+    pub values: Vec<f64>,
+    /// Chips the whole range expects to win from the pot.
+    pub value: f64,
+    /// Action picked per action node of the player, per stored runout and
+    /// combo.
+    actions: Vec<Vec<u8>>,
+}
+
+/// Board, average strategy of the acting player (one row of combos per
+/// action) and their expected values at a stored runout of a node.
+pub(crate) type StoredSpot = (u64, Vec<f64>, Vec<f64>);
+
+/// What `values` writes down at the traverser's action nodes, per stored
+/// runout and combo. Empty tables are skipped.
+#[derive(Debug, Clone, Default)]
+struct Record {
+    /// Action a best response picks.
+    actions: Vec<Vec<u8>>,
+    /// Chips each combo expects to win from the pot.
+    values: Vec<Vec<f64>>,
+}
+
+/// Frequencies fixed at a locked node, one row per action and one entry
+/// per combo of the acting player. Rows not `fixed` are left to the solver.
+#[derive(Debug, Clone)]
+struct Lock {
+    fixed: Vec<bool>,
+    frequencies: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    regrets: Vec<Vec<f32>>,
+    strategy_sum: Vec<Vec<f32>>,
+}
+
+/// Heads-up postflop subgame solved by counterfactual regret minimization,
+/// from the flop, turn or river. Player 0 is out of position. Strategies
+/// are kept per action node, per suit canonical runout, per action and per
+/// combo of the acting player; cards that are suit isomorphic given the
+/// board and both ranges share one runout.
+#[derive(Debug, Clone)]
+pub struct CfrSolver {
+    pub tree: ActionTree,
+    pub board: u64,
+    pub algorithm: CfrAlgorithm,
+    pub iterations: u32,
+    combos: [Vec<Combo>; 2],
+    /// Card removal between the ranges, from each player's side.
+    showdown: [Showdown; 2],
+    swaps: Vec<Swap>,
+    runouts: Vec<Runout>,
+    tables: Tables,
+    locks: Vec<Option<Lock>>,
+}
+
+/// Regret matching: positive regrets normalized per combo, uniform when
+/// none are positive. `values` holds `actions` rows of `n` entries.
+fn regret_match(values: &[f32], actions: usize, n: usize) -> Vec<f64> {
+    let mut out = vec![0.0f64; actions * n];
+    for h in 0..n {
+        let total: f64 = (0..actions)
+            .map(|a| values[a * n + h].max(0.0) as f64)
+            .sum();
+        for a in 0..actions {
+            out[a * n + h] = if total > 0.0 {
+                values[a * n + h].max(0.0) as f64 / total
+            } else {
+                1.0 / actions as f64
+            };
+        }
+    }
+    out
+}
+
+/// Exchange suits `a` < `b` of a card mask.
+fn swap_suits(mask: u64, (a, b): (u32, u32)) -> u64 {
+    let (ma, mb) = (unpack_suit(0x1fff, a), unpack_suit(0x1fff, b));
+    let shift = b - a;
+    mask & !(ma | mb) | (mask & ma) << shift | (mask & mb) >> shift
+}
+
+/// Suit transpositions under which both players' combos and weights are
+/// unchanged.
+fn range_swaps(combos: &[Vec<Combo>; 2]) -> Vec<Swap> {
+    let index: Vec<HashMap<u64, usize>> = combos
+        .iter()
+        .map(|side| side.iter().enumerate().map(|(i, c)| (c.mask, i)).collect())
+        .collect();
+    let mut out = Vec::new();
+    for a in 0..SUIT_COUNT {
+        for b in a + 1..SUIT_COUNT {
+            let map = |p: usize| -> Option<Vec<usize>> {
+                combos[p]
+                    .iter()
+                    .map(|c| {
+                        index[p]
+                            .get(&swap_suits(c.mask, (a, b)))
+                            .copied()
+                            .filter(|&j| combos[p][j].weight == c.weight)
+                    })
+                    .collect()
+            };
+            if let (Some(c0), Some(c1)) = (map(0), map(1)) {
+                out.push(Swap {
+                    suits: (a, b),
+                    combo: [c0, c1],
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Enumerate the runouts from `board` on `street`, dealing only one card
+/// per suit isomorphic class, and return the index of the first.
+fn deal_runouts(
+    runouts: &mut Vec<Runout>,
+    slots: &mut [usize],
+    swaps: &[Swap],
+    masks: &[Vec<u64>; 2],
+    board: u64,
+    street: usize,
+) -> usize {
+    let id = runouts.len();
+    runouts.push(Runout {
+        board,
+        slot: slots[street],
+        next: Vec::new(),
+        ranking: None,
+    });
+    slots[street] += 1;
+    if street + 1 == slots.len() {
+        runouts[id].ranking = Some([
+            Ranking::new(&masks[0], board),
+            Ranking::new(&masks[1], board),
+        ]);
+        return id;
+    }
+    let valid: Vec<usize> = (0..swaps.len())
+        .filter(|&k| swap_suits(board, swaps[k].suits) == board)
+        .collect();
+    let mut next: Vec<Deal> = Vec::new();
+    for card in 0..52u32 {
+        if board & 1u64 << card != 0 {
+            continue;
+        }
+        let canonical = valid
+            .iter()
+            .map(|&k| (swap_suits(1u64 << card, swaps[k].suits).trailing_zeros(), k))
+            .filter(|&(c, _)| c < card)
+            .min();
+        let deal = match canonical {
+            Some((c, k)) => {
+                let from = next.iter().position(|d| d.card == c).unwrap();
+                Deal {
+                    card,
+                    runout: next[from].runout,
+                    swap: Some(k),
+                    from,
+                }
+            }
+            None => Deal {
+                card,
+                runout: deal_runouts(
+                    runouts,
+                    slots,
+                    swaps,
+                    masks,
+                    board | 1u64 << card,
+                    street + 1,
+                ),
+                swap: None,
+                from: next.len(),
+            },
+        };
+        next.push(deal);
+    }
+    runouts[id].next = next;
+    id
+}
+
+impl CfrSolver {
+    /// Solver for `board` (3 to 5 cards) with the out of position player's
+    /// range first. `config` needs one street of sizes per street left.
+    pub fn new(board: u64, ranges: [&Range; 2], config: TreeConfig) -> Result<Self, String> {
+        let cards = board.count_ones() as usize;
+        if !(3..=5).contains(&cards) {
+            return Err("board needs 3 to 5 cards".into());
+        }
+        if config.streets.len() != 6 - cards {
+            return Err(format!(
+                "a {} card board needs sizes for {} streets",
+                cards,
+                6 - cards
+            ));
+        }
+        let combos: Vec<Vec<Combo>> = ranges
+            .iter()
+            .map(|r| {
+                r.live_combos(board)
+                    .filter(|c| c.weight > 0.0)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        for c in &combos {
+            if c.is_empty() {
+                return Err("range has no live combos".into());
+            }
+            if c.iter().any(|c| c.mask.count_ones() != 2) {
+                return Err("ranges must hold 2 card combos".into());
+            }
+        }
+        let combos = [combos[0].clone(), combos[1].clone()];
+        let masks = [0, 1].map(|p| combos[p].iter().map(|c| c.mask).collect::<Vec<u64>>());
+        let showdown = [
+            Showdown::new(&masks[0], &masks[1]),
+            Showdown::new(&masks[1], &masks[0]),
+        ];
+        let swaps = range_swaps(&combos);
+        let tree = ActionTree::new(config)?;
+        let locks = vec![None; tree.len()];
+        let mut solver = Self {
+            tree,
+            board,
+            algorithm: CfrAlgorithm::Discounted,
+            iterations: 0,
+            combos,
+            showdown,
+            swaps,
+            runouts: Vec::new(),
+            tables: Tables::default(),
+            locks,
+        };
+        solver.rebuild();
+        Ok(solver)
+    }
+
+    /// Deal the runouts again for the current swaps and start the solve
+    /// over.
+    fn rebuild(&mut self) {
+        let masks = [0, 1].map(|p| self.combos[p].iter().map(|c| c.mask).collect::<Vec<u64>>());
+        let mut runouts = Vec::new();
+        let mut slots = vec![0usize; self.tree.config.streets.len()];
+        deal_runouts(&mut runouts, &mut slots, &self.swaps, &masks, self.board, 0);
+        let sizes: Vec<Vec<f32>> = self
+            .tree
+            .nodes
+            .iter()
+            .map(|n| match &n.kind {
+                NodeKind::Action {
+                    player, actions, ..
+                } => vec![0.0; slots[n.street] * actions.len() * self.combos[*player].len()],
+                _ => Vec::new(),
+            })
+            .collect();
+        self.runouts = runouts;
+        self.tables = Tables {
+            regrets: sizes.clone(),
+            strategy_sum: sizes,
+        };
+        self.iterations = 0;
+    }
+
+    /// Fix the acting player's frequencies at action node `node` on every
+    /// runout: one row per action, `Some` with one frequency per combo in
+    /// `combos` order, or `None` to let the solver share what the fixed
+    /// rows leave among those actions. Best responses are free to leave
+    /// their own player's locks. A lock that breaks a suit symmetry of the
+    /// ranges stops runouts sharing through it and restarts the solve.
+    pub fn lock(&mut self, node: usize, rows: &[Option<Vec<f64>>]) -> Result<(), String> {
+        let (player, actions) = match self.tree.nodes.get(node).map(|n| &n.kind) {
+            Some(NodeKind::Action {
+                player, actions, ..
+            }) => (*player, actions.len()),
+            _ => return Err(format!("node {} is not an action node", node)),
+        };
+        let n = self.combos[player].len();
+        if rows.len() != actions {
+            return Err(format!("node {} has {} actions", node, actions));
+        }
+        if rows.iter().flatten().any(|row| row.len() != n) {
+            return Err(format!("need a frequency for each of {} combos", n));
+        }
+        let fixed: Vec<bool> = rows.iter().map(Option::is_some).collect();
+        let mut frequencies = vec![0.0; actions * n];
+        for (a, row) in rows.iter().enumerate() {
+            if let Some(row) = row {
+                if row.iter().any(|f| !(0.0..=1.0).contains(f)) {
+                    return Err("frequencies must be between 0 and 1".into());
+                }
+                frequencies[a * n..(a + 1) * n].copy_from_slice(row);
+            }
+        }
+        let all_fixed = fixed.iter().all(|&f| f);
+        for h in 0..n {
+            let total: f64 = (0..actions).map(|a| frequencies[a * n + h]).sum();
+            if total > 1.0 + 1e-6 || all_fixed && total < 1.0 - 1e-6 {
+                return Err(format!(
+                    "frequencies of combo {} add up to {}",
+                    Hand {
+                        mask: self.combos[player][h].mask
+                    },
+                    total
+                ));
+            }
+        }
+
+        let symmetric = |swap: &Swap| {
+            swap.combo[player].iter().enumerate().all(|(h, &j)| {
+                (0..actions).all(|a| frequencies[a * n + h] == frequencies[a * n + j])
+            })
+        };
+        let before = self.swaps.len();
+        self.swaps.retain(symmetric);
+        self.locks[node] = Some(Lock { fixed, frequencies });
+        if self.swaps.len() != before && self.tree.config.streets.len() > 1 {
+            self.rebuild();
+        }
+        Ok(())
+    }
+
+    /// Lock every combo at `node` into taking `action` a `fraction` of the
+    /// time, such as folding 70% to a bet, leaving the other actions to
+    /// the solver.
+    pub fn lock_action(&mut self, node: usize, action: usize, fraction: f64) -> Result<(), String> {
+        let (player, actions) = match self.tree.nodes.get(node).map(|n| &n.kind) {
+            Some(NodeKind::Action {
+                player, actions, ..
+            }) => (*player, actions.len()),
+            _ => return Err(format!("node {} is not an action node", node)),
+        };
+        if action >= actions {
+            return Err(format!("node {} has {} actions", node, actions));
+        }
+        let mut rows = vec![None; actions];
+        rows[action] = Some(vec![fraction; self.combos[player].len()]);
+        self.lock(node, &rows)
+    }
+
+    /// Let the solver pick the strategy at `node` again.
+    pub fn unlock(&mut self, node: usize) {
+        if let Some(lock) = self.locks.get_mut(node) {
+            *lock = None;
+        }
+    }
+
+    /// Put the locked frequencies of `node` into `sigma`, the free actions
+    /// sharing what is left in proportion to their probabilities.
+    fn constrain(&self, node: usize, sigma: &mut [f64]) {
+        let Some(lock) = &self.locks[node] else {
+            return;
+        };
+        let actions = lock.fixed.len();
+        let free = lock.fixed.iter().filter(|&&f| !f).count();
+        let n = sigma.len() / actions;
+        for h in 0..n {
+            let (mut left, mut share) = (1.0f64, 0.0);
+            for (a, &fixed) in lock.fixed.iter().enumerate() {
+                if fixed {
+                    left -= lock.frequencies[a * n + h];
+                } else {
+                    share += sigma[a * n + h];
+                }
+            }
+            let left = left.max(0.0);
+            for (a, &fixed) in lock.fixed.iter().enumerate() {
+                let i = a * n + h;
+                sigma[i] = if fixed {
+                    lock.frequencies[i]
+                } else if share > 0.0 {
+                    sigma[i] * left / share
+                } else {
+                    left / free as f64
+                };
+            }
+        }
+    }
+
+    /// Live combos of `player`, in the order strategies are reported.
+    pub fn combos(&self, player: usize) -> &[Combo] {
+        &self.combos[player]
+    }
+
+    /// Boards stored after suit isomorphism, the starting board included.
+    pub fn runouts(&self) -> usize {
+        self.runouts.len()
+    }
+
+    /// Bytes held by regrets and strategy sums.
+    pub fn memory_usage(&self) -> usize {
+        let floats: usize = self.tables.regrets.iter().map(Vec::len).sum();
+        2 * floats * std::mem::size_of::<f32>()
+    }
+
+    fn weights(&self, player: usize) -> Vec<f64> {
+        self.combos[player]
+            .iter()
+            .map(|c| c.weight as f64)
+            .collect()
+    }
+
+    /// `weights` of `player`'s combos with those holding a card of `dead`
+    /// zeroed.
+    fn live(&self, player: usize, weights: &[f64], dead: u64) -> Vec<f64> {
+        self.combos[player]
+            .iter()
+            .zip(weights)
+            .map(|(c, &w)| if c.mask & dead == 0 { w } else { 0.0 })
+            .collect()
+    }
+
+    /// One iteration: each player's regrets are updated in turn.
+    pub fn iterate(&mut self) {
+        self.iterations += 1;
+        let mut tables = std::mem::take(&mut self.tables);
+        for player in 0..2 {
+            let reach = self.weights(player);
+            let opponent = self.weights(1 - player);
+            self.cfr(&mut tables, 0, 0, player, &reach, &opponent);
+        }
+        self.tables = tables;
+    }
+
+    /// Run `iterations` more iterations and return the exploitability.
+    pub fn solve(&mut self, iterations: u32) -> f64 {
+        for _ in 0..iterations {
+            self.iterate();
+        }
+        self.exploitability()
+    }
+
+    /// Iterate until the exploitability is at most `target` chips or
+    /// `max_iterations` have run in total, measuring it every `check_every`
+    /// iterations and passing the iteration count and exploitability to
+    /// `progress`. Returns the last exploitability measured.
+    pub fn solve_until(
+        &mut self,
+        max_iterations: u32,
+        target: f64,
+        check_every: u32,
+        mut progress: impl FnMut(u32, f64),
+    ) -> f64 {
+        let mut exploitability = self.exploitability();
+        while self.iterations < max_iterations && exploitability > target {
+            let batch = check_every.max(1).min(max_iterations - self.iterations);
+            exploitability = self.solve(batch);
+            progress(self.iterations, exploitability);
+        }
+        exploitability
+    }
+
+    /// Acting player, number of actions, stored runout slot and the stored
+    /// combo standing for each of the player's combos at `node` once
+    /// `dealt` (one card per street before the node's) came.
+    fn locate(
+        &self,
+        node: usize,
+        dealt: &[Card],
+    ) -> Result<(usize, usize, usize, Vec<usize>), String> {
+        let (player, actions) = match &self.tree.nodes[node].kind {
+            NodeKind::Action {
+                player, actions, ..
+            } => (*player, actions.len()),
+            _ => return Err(format!("node {} is not an action node", node)),
+        };
+        let street = self.tree.nodes[node].street;
+        if dealt.len() != street {
+            return Err(format!(
+                "node {} is {} streets in, got {} cards",
+                node,
+                street,
+                dealt.len()
+            ));
+        }
+        // suit of the stored runout standing for each actual suit
+        let mut suit: [u32; 4] = [0, 1, 2, 3];
+        let mut runout = 0;
+        for card in dealt {
+            let c = card.rank() as u32 * SUIT_COUNT + suit[card.suit() as usize];
+            let deal = self.runouts[runout]
+                .next
+                .iter()
+                .find(|d| d.card == c)
+                .ok_or_else(|| format!("{} is already dealt", card))?;
+            if let Some(k) = deal.swap {
+                let (a, b) = self.swaps[k].suits;
+                for s in suit.iter_mut() {
+                    if *s == a {
+                        *s = b;
+                    } else if *s == b {
+                        *s = a;
+                    }
+                }
+            }
+            runout = deal.runout;
+        }
+        let index: HashMap<u64, usize> = self.combos[player]
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.mask, i))
+            .collect();
+        let stored: Vec<usize> = self.combos[player]
+            .iter()
+            .map(|c| {
+                let mut mask = 0u64;
+                for (s, &to) in suit.iter().enumerate() {
+                    mask |= unpack_suit(pack_suit(c.mask, s as u32), to);
+                }
+                index[&mask]
+            })
+            .collect();
+        Ok((player, actions, self.runouts[runout].slot, stored))
+    }
+
+    /// Average strategy at action node `node` once `dealt` (one card per
+    /// street before the node's) came: one row per action, one probability
+    /// per combo of the acting player.
+    pub fn strategy(&self, node: usize, dealt: &[Card]) -> Result<Vec<Vec<f64>>, String> {
+        let (player, actions, slot, stored) = self.locate(node, dealt)?;
+        let n = self.combos[player].len();
+        let size = actions * n;
+        let mut sigma = regret_match(
+            &self.tables.strategy_sum[node][slot * size..(slot + 1) * size],
+            actions,
+            n,
+        );
+        self.constrain(node, &mut sigma);
+        Ok((0..actions)
+            .map(|a| stored.iter().map(|&j| sigma[a * n + j]).collect())
+            .collect())
+    }
+
+    /// One entry per stored runout and combo at each of `player`'s action
+    /// nodes, nothing elsewhere.
+    fn per_combo<T: Clone + Default>(&self, player: usize) -> Vec<Vec<T>> {
+        self.tree
+            .nodes
+            .iter()
+            .zip(&self.tables.regrets)
+            .map(|(n, r)| match &n.kind {
+                NodeKind::Action {
+                    player: p, actions, ..
+                } if *p == player => vec![T::default(); r.len() / actions.len()],
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// `player`'s best response to the opponent's average strategy. To
+    /// respond to a fixed strategy instead, such as one from population
+    /// reads, `lock` the opponent's nodes to it first: locked nodes play
+    /// the locked frequencies, the rest the average strategy.
+    pub fn best_response(&self, player: usize) -> BestResponse {
+        let mut record = Record {
+            actions: self.per_combo(player),
+            values: Vec::new(),
+        };
+        let opponent = self.weights(1 - player);
+        let values = self.values(
+            0,
+            0,
+            player,
+            &opponent,
+            Mode::BestResponse,
+            Some(&mut record),
+        );
+        let (value, per_combo) = self.normalize(player, &values);
+        BestResponse {
+            player,
+            values: per_combo,
+            value,
+            actions: record.actions,
+        }
+    }
+
+    /// Every stored runout of every action node, in `combos` order.
+    pub(crate) fn spots(&self) -> Vec<Vec<StoredSpot>> {
+        let mut values: Vec<Vec<f64>> = vec![Vec::new(); self.tree.len()];
+        for player in 0..2 {
+            let mut record = Record {
+                actions: Vec::new(),
+                values: self.per_combo(player),
+            };
+            let opponent = self.weights(1 - player);
+            self.values(0, 0, player, &opponent, Mode::Average, Some(&mut record));
+            for (x, v) in values.iter_mut().zip(record.values) {
+                if !v.is_empty() {
+                    *x = v;
+                }
+            }
+        }
+        let cards = self.board.count_ones();
+        self.tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(node, n)| {
+                let NodeKind::Action {
+                    player, actions, ..
+                } = &n.kind
+                else {
+                    return Vec::new();
+                };
+                let (actions, n_combos) = (actions.len(), self.combos[*player].len());
+                let size = actions * n_combos;
+                self.runouts
+                    .iter()
+                    .filter(|r| (r.board.count_ones() - cards) as usize == n.street)
+                    .map(|r| {
+                        let mut sigma = regret_match(
+                            &self.tables.strategy_sum[node][r.slot * size..(r.slot + 1) * size],
+                            actions,
+                            n_combos,
+                        );
+                        self.constrain(node, &mut sigma);
+                        let ev = values[node][r.slot * n_combos..(r.slot + 1) * n_combos].to_vec();
+                        (r.board, sigma, ev)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Action index the best response `response` takes with each combo at
+    /// `node` once `dealt` came.
+    pub fn response_actions(
+        &self,
+        response: &BestResponse,
+        node: usize,
+        dealt: &[Card],
+    ) -> Result<Vec<usize>, String> {
+        let (player, actions, slot, stored) = self.locate(node, dealt)?;
+        if player != response.player {
+            return Err(format!("node {} belongs to the other player", node));
+        }
+        let n = self.combos[player].len();
+        let chosen = &response.actions[node][slot * n..(slot + 1) * n];
+        debug_assert!(chosen.iter().all(|&a| (a as usize) < actions));
+        Ok(stored.iter().map(|&j| chosen[j] as usize).collect())
+    }
+
+    /// Board and action index per combo, in `combos` order, at every stored
+    /// runout of every action node of `response`'s player, nothing
+    /// elsewhere. Combos the board blocks read 0.
+    pub fn response_spots(&self, response: &BestResponse) -> Vec<Vec<(u64, Vec<usize>)>> {
+        let cards = self.board.count_ones();
+        self.tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(node, n)| match &n.kind {
+                NodeKind::Action { player, .. } if *player == response.player => {
+                    let n_combos = self.combos[*player].len();
+                    self.runouts
+                        .iter()
+                        .filter(|r| (r.board.count_ones() - cards) as usize == n.street)
+                        .map(|r| {
+                            let chosen =
+                                &response.actions[node][r.slot * n_combos..(r.slot + 1) * n_combos];
+                            (r.board, chosen.iter().map(|&a| a as usize).collect())
+                        })
+                        .collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// `exploitability` in thousandths of a big blind of `big_blind` chips.
+    pub fn exploitability_mbb(&self, big_blind: f64) -> f64 {
+        self.exploitability() / big_blind * 1000.0
+    }
+
+    /// Chips the best responses gain over the game value, averaged over
+    /// both players.
+    pub fn exploitability(&self) -> f64 {
+        let best: f64 = (0..2)
+            .map(|p| self.range_value(p, Mode::BestResponse))
+            .sum();
+        (best - self.tree.config.pot) / 2.0
+    }
+
+    /// `player`'s expected chips won from the pot, per combo, when both play
+    /// the average strategy.
+    pub fn expected_values(&self, player: usize) -> Vec<f64> {
+        let values = self.values(0, 0, player, &self.weights(1 - player), Mode::Average, None);
+        self.normalize(player, &values).1
+    }
+
+    /// Range value and per combo values from counterfactual values.
+    fn normalize(&self, player: usize, values: &[f64]) -> (f64, Vec<f64>) {
+        let own = self.weights(player);
+        let faced = self.showdown[player].compatible(&self.weights(1 - player));
+        let total: f64 = own.iter().zip(&faced).map(|(w, f)| w * f).sum();
+        let value: f64 = own.iter().zip(values).map(|(w, v)| w * v).sum();
+        let per_combo = values
+            .iter()
+            .zip(faced)
+            .map(|(v, f)| if f > 0.0 { v / f } else { 0.0 })
+            .collect();
+        (value / total, per_combo)
+    }
+
+    /// Expected chips won by `player`'s whole range.
+    fn range_value(&self, player: usize, mode: Mode) -> f64 {
+        let values = self.values(0, 0, player, &self.weights(1 - player), mode, None);
+        self.normalize(player, &values).0
+    }
+
+    /// Counterfactual value of each of `player`'s combos at a terminal.
+    fn terminal(&self, node: usize, runout: usize, player: usize, opponent: &[f64]) -> Vec<f64> {
+        let n = &self.tree.nodes[node];
+        let pot = self.tree.pot(node);
+        let spent = n.commit[player];
+        match n.kind {
+            NodeKind::Fold { folder } => {
+                let payoff = if folder == player {
+                    -spent
+                } else {
+                    pot - spent
+                };
+                self.showdown[player]
+                    .compatible(opponent)
+                    .into_iter()
+                    .map(|f| f * payoff)
+                    .collect()
+            }
+            _ => {
+                let ranking = self.runouts[runout]
+                    .ranking
+                    .as_ref()
+                    .expect("showdown before the river");
+                self.showdown[player]
+                    .matchups(&ranking[player], &ranking[1 - player], opponent)
+                    .into_iter()
+                    .map(|m| pot * (m.win + m.tie / 2.0) - spent * m.total)
+                    .collect()
+            }
+        }
+    }
+
+    /// Average of `visit` over the cards dealt at a chance node. `visit`
+    /// gets the runout reached and the dealt card's mask; `player`'s
+    /// combos holding the card are worth nothing. Isomorphic cards reuse
+    /// the canonical card's values with the combos swapped.
+    fn chance(
+        &self,
+        runout: usize,
+        player: usize,
+        mut visit: impl FnMut(usize, u64) -> Vec<f64>,
+    ) -> Vec<f64> {
+        let r = &self.runouts[runout];
+        let unseen = (52 - r.board.count_ones() - 4) as f64;
+        let mut out = vec![0.0; self.combos[player].len()];
+        let mut dealt: Vec<Vec<f64>> = Vec::with_capacity(r.next.len());
+        for deal in &r.next {
+            let values = match deal.swap {
+                None => {
+                    let bit = 1u64 << deal.card;
+                    let values = visit(deal.runout, bit);
+                    self.live(player, &values, bit)
+                }
+                Some(k) => self.swaps[k].combo[player]
+                    .iter()
+                    .map(|&j| dealt[deal.from][j])
+                    .collect(),
+            };
+            for (x, v) in out.iter_mut().zip(&values) {
+                *x += v / unseen;
+            }
+            dealt.push(values);
+        }
+        out
+    }
+
+    fn cfr(
+        &self,
+        tables: &mut Tables,
+        node: usize,
+        runout: usize,
+        player: usize,
+        reach: &[f64],
+        opponent: &[f64],
+    ) -> Vec<f64> {
+        let (actor, children) = match &self.tree.nodes[node].kind {
+            NodeKind::Action {
+                player, children, ..
+            } => (*player, children),
+            &NodeKind::Chance { child } => {
+                return self.chance(runout, player, |next, bit| {
+                    let reach = self.live(player, reach, bit);
+                    let opponent = self.live(1 - player, opponent, bit);
+                    self.cfr(tables, child, next, player, &reach, &opponent)
+                })
+            }
+            _ => return self.terminal(node, runout, player, opponent),
+        };
+        let actions = children.len();
+        let n = self.combos[actor].len();
+        let offset = self.runouts[runout].slot * actions * n;
+        let stored = offset..offset + actions * n;
+        let mut sigma = regret_match(&tables.regrets[node][stored.clone()], actions, n);
+        self.constrain(node, &mut sigma);
+        if actor != player {
+            let mut out = vec![0.0; reach.len()];
+            for (a, &child) in children.iter().enumerate() {
+                let next: Vec<f64> = (0..n).map(|o| opponent[o] * sigma[a * n + o]).collect();
+                for (x, v) in out
+                    .iter_mut()
+                    .zip(self.cfr(tables, child, runout, player, reach, &next))
+                {
+                    *x += v;
+                }
+            }
+            return out;
+        }
+
+        let mut values = Vec::with_capacity(actions);
+        for (a, &child) in children.iter().enumerate() {
+            let next: Vec<f64> = (0..n).map(|h| reach[h] * sigma[a * n + h]).collect();
+            values.push(self.cfr(tables, child, runout, player, &next, opponent));
+        }
+        let node_value: Vec<f64> = (0..n)
+            .map(|h| (0..actions).map(|a| sigma[a * n + h] * values[a][h]).sum())
+            .collect();
+
+        let t = self.iterations as f64;
+        let (positive, negative, keep) = match self.algorithm {
+            CfrAlgorithm::CfrPlus => (1.0, 0.0, 1.0),
+            CfrAlgorithm::Discounted => {
+                let p = t.powf(1.5);
+                (p / (p + 1.0), 0.5, ((t - 1.0) / t).powi(2))
+            }
+        };
+        let weight = match self.algorithm {
+            CfrAlgorithm::CfrPlus => t,
+            CfrAlgorithm::Discounted => 1.0,
+        };
+        let regrets = &mut tables.regrets[node][stored.clone()];
+        let sums = &mut tables.strategy_sum[node][stored];
+        for (a, action_values) in values.iter().enumerate() {
+            for h in 0..n {
+                let i = a * n + h;
+                let r = regrets[i] as f64;
+                let r = if r > 0.0 { r * positive } else { r * negative };
+                let r = r + action_values[h] - node_value[h];
+                regrets[i] = match self.algorithm {
+                    CfrAlgorithm::CfrPlus => r.max(0.0),
+                    CfrAlgorithm::Discounted => r,
+                } as f32;
+                sums[i] = (sums[i] as f64 * keep + weight * reach[h] * sigma[i]) as f32;
+            }
+        }
+        node_value
+    }
+
+    /// Counterfactual values without updating anything, written down at the
+    /// traverser's nodes into `record`.
+    fn values(
+        &self,
+        node: usize,
+        runout: usize,
+        player: usize,
+        opponent: &[f64],
+        mode: Mode,
+        mut record: Option<&mut Record>,
+    ) -> Vec<f64> {
+        let (actor, children) = match &self.tree.nodes[node].kind {
+            NodeKind::Action {
+                player, children, ..
+            } => (*player, children),
+            &NodeKind::Chance { child } => {
+                return self.chance(runout, player, |next, bit| {
+                    let opponent = self.live(1 - player, opponent, bit);
+                    self.values(child, next, player, &opponent, mode, record.as_deref_mut())
+                })
+            }
+            _ => return self.terminal(node, runout, player, opponent),
+        };
+        let actions = children.len();
+        let n = self.combos[actor].len();
+        let slot = self.runouts[runout].slot;
+        let offset = slot * actions * n;
+        let mut sigma = regret_match(
+            &self.tables.strategy_sum[node][offset..offset + actions * n],
+            actions,
+            n,
+        );
+        self.constrain(node, &mut sigma);
+        if actor != player {
+            let mut out = vec![0.0; self.combos[player].len()];
+            for (a, &child) in children.iter().enumerate() {
+                let next: Vec<f64> = (0..n).map(|o| opponent[o] * sigma[a * n + o]).collect();
+                let v = self.values(child, runout, player, &next, mode, record.as_deref_mut());
+                for (x, v) in out.iter_mut().zip(v) {
+                    *x += v;
+                }
+            }
+            return out;
+        }
+        let mut out = match mode {
+            Mode::Average => vec![0.0; n],
+            Mode::BestResponse => vec![f64::MIN; n],
+        };
+        let mut best = vec![0u8; n];
+        for (a, &child) in children.iter().enumerate() {
+            let v = self.values(child, runout, player, opponent, mode, record.as_deref_mut());
+            for h in 0..n {
+                match mode {
+                    Mode::Average => out[h] += sigma[a * n + h] * v[h],
+                    Mode::BestResponse => {
+                        if v[h] > out[h] {
+                            out[h] = v[h];
+                            best[h] = a as u8;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(record) = record {
+            let stored = slot * n..(slot + 1) * n;
+            if mode == Mode::BestResponse && !record.actions.is_empty() {
+                record.actions[node][stored.clone()].copy_from_slice(&best);
+            }
+            if !record.values.is_empty() {
+                let faced = self.showdown[player].compatible(opponent);
+                for ((x, v), f) in record.values[node][stored].iter_mut().zip(&out).zip(faced) {
+                    *x = if f > 0.0 { v / f } else { 0.0 };
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::poker::evaluator::eval;
+    use crate::poker::tree::{Action, StreetSizes, IP, OOP};
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    pub(crate) fn pot_bet_only() -> TreeConfig {
+        TreeConfig {
+            pot: 100.0,
+            stack: 1000.0,
+            streets: vec![StreetSizes::new(&[1.0], &[])],
+            max_raises: 1,
+            all_in: false,
+            all_in_threshold: 1.0,
+        }
+    }
+
+    /// Sets and air out of position against a bluff catcher on a dry river.
+    pub(crate) fn sets_and_air(config: TreeConfig) -> CfrSolver {
+        let oop = Range::from_notation("99,JTs").unwrap();
+        let ip = Range::from_notation("KQo").unwrap();
+        CfrSolver::new(mask("Kh9d6c3s2s"), [&oop, &ip], config).unwrap()
+    }
+
+    #[test]
+    fn nuts_versus_air_check_down() {
+        let mut config = pot_bet_only();
+        config.streets[0].bet.clear();
+        config.max_raises = 0;
+        let oop = Range::from_notation("99").unwrap();
+        let ip = Range::from_notation("JTs").unwrap();
+        let mut solver = CfrSolver::new(mask("Kh9d6c3s2s"), [&oop, &ip], config).unwrap();
+        assert!(solver.solve(1).abs() < 1e-9);
+        assert!(solver.expected_values(OOP).iter().all(|&v| v == 100.0));
+        assert!(solver.expected_values(IP).iter().all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn polarized_bluffing_frequency() {
+        // sets and air against a bluff catcher: with a pot sized bet OOP
+        // bluffs half as often as it value bets and IP calls half the time
+        let mut solver = sets_and_air(pot_bet_only());
+        let exploitability = solver.solve(2000);
+        assert!(exploitability < 0.5, "exploitability {}", exploitability);
+
+        let root = solver.strategy(0, &[]).unwrap();
+        let bet = &root[1];
+        let combos = solver.combos(OOP);
+        let (mut value, mut bluff) = (0.0, 0.0);
+        for (c, &p) in combos.iter().zip(bet) {
+            if (Hand { mask: c.mask }).to_string().contains('9') {
+                value += p;
+            } else {
+                bluff += p;
+            }
+        }
+        assert!((value - 3.0).abs() < 0.05, "value bets {}", value);
+        assert!((bluff - 1.5).abs() < 0.1, "bluffs {}", bluff);
+
+        let facing = match &solver.tree.nodes[0].kind {
+            NodeKind::Action {
+                actions, children, ..
+            } => {
+                assert_eq!(actions[1], Action::Bet(100.0));
+                children[1]
+            }
+            _ => unreachable!(),
+        };
+        let call = &solver.strategy(facing, &[]).unwrap()[1];
+        let freq = call.iter().sum::<f64>() / call.len() as f64;
+        assert!((freq - 0.5).abs() < 0.05, "call frequency {}", freq);
+
+        let ev = solver.expected_values(OOP);
+        let total: f64 = ev.iter().sum::<f64>() / ev.len() as f64;
+        assert!((total - 450.0 / 7.0).abs() < 1.0, "oop value {}", total);
+    }
+
+    #[test]
+    fn wide_ranges_converge() {
+        let config = TreeConfig {
+            streets: vec![StreetSizes::new(&[0.75], &[1.0])],
+            max_raises: 2,
+            ..TreeConfig::default()
+        };
+        let oop = Range::from_notation("22+,A2s+,K9s+,QTs+,JTs,ATo+,KJo+").unwrap();
+        let ip = Range::from_notation("55+,A8s+,KTs+,QJs,AJo+,KQo").unwrap();
+        let mut solver = CfrSolver::new(mask("Qs8h5d3c2s"), [&oop, &ip], config).unwrap();
+        let early = solver.solve(10);
+        let late = solver.solve(190);
+        assert!(late < early);
+        assert!(late < 0.01 * 100.0, "exploitability {}", late);
+        for node in 0..solver.tree.len() {
+            let Ok(strategy) = solver.strategy(node, &[]) else {
+                continue;
+            };
+            for h in 0..strategy[0].len() {
+                let sum: f64 = strategy.iter().map(|row| row[h]).sum();
+                assert!((sum - 1.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn cfr_plus_converges() {
+        let mut solver = sets_and_air(pot_bet_only());
+        solver.algorithm = CfrAlgorithm::CfrPlus;
+        assert!(solver.solve(1000) < 0.5);
+    }
+
+    fn check_down(streets: usize) -> TreeConfig {
+        TreeConfig {
+            streets: vec![StreetSizes::new(&[], &[]); streets],
+            max_raises: 0,
+            ..TreeConfig::default()
+        }
+    }
+
+    /// OOP's share of the pot when checking down, by brute force.
+    fn check_down_value(board: u64, oop: &Range, ip: &Range) -> f64 {
+        let (mut total, mut count) = (0.0, 0.0);
+        for h in oop.live_combos(board) {
+            for v in ip.live_combos(board | h.mask) {
+                let weight = (h.weight * v.weight) as f64;
+                for card in 0..52 {
+                    let river = board | 1u64 << card;
+                    if river == board || river & (h.mask | v.mask) != 0 {
+                        continue;
+                    }
+                    let (a, b) = (eval(h.mask | river), eval(v.mask | river));
+                    let share = if a > b {
+                        1.0
+                    } else if a == b {
+                        0.5
+                    } else {
+                        0.0
+                    };
+                    total += weight * share;
+                    count += weight;
+                }
+            }
+        }
+        100.0 * total / count
+    }
+
+    #[test]
+    fn turn_chance_node_matches_brute_force() {
+        // symmetric ranges on a monotone board share runouts across the
+        // three other suits; QsQh only leaves diamonds and clubs swappable
+        let board = mask("Ks9s6s3s");
+        let cases = [
+            ("AA", "QQ", 1 + 9 + 13),
+            ("AA", "QQ,87", 1 + 9 + 13),
+            ("AA", "QhQd,QhQc,QdQc,QsQh", 1 + 9 + 13 + 13),
+        ];
+        for (oop, ip, runouts) in cases {
+            let oop = Range::from_notation(oop).unwrap();
+            let ip = Range::from_notation(ip).unwrap();
+            let mut solver = CfrSolver::new(board, [&oop, &ip], check_down(2)).unwrap();
+            assert_eq!(solver.runouts(), runouts);
+            assert!(solver.solve(1).abs() < 1e-9);
+            let value = solver.range_value(OOP, Mode::Average);
+            let expected = check_down_value(board, &oop, &ip);
+            assert!((value - expected).abs() < 1e-6, "{} vs {}", value, expected);
+        }
+        let oop = Range::from_notation("AhAd").unwrap();
+        let ip = Range::from_notation("QhQd").unwrap();
+        let mut solver = CfrSolver::new(mask("Kc9d6c3s"), [&oop, &ip], check_down(2)).unwrap();
+        solver.solve(1);
+        // two queens out of 44 unseen cards
+        assert!((solver.expected_values(OOP)[0] - 100.0 * 42.0 / 44.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn turn_strategy_per_river_card() {
+        let config = TreeConfig {
+            streets: vec![StreetSizes::new(&[], &[]), StreetSizes::new(&[1.0], &[])],
+            max_raises: 1,
+            all_in: false,
+            ..TreeConfig::default()
+        };
+        let oop = Range::from_notation("AA,76s").unwrap();
+        let ip = Range::from_notation("KQo").unwrap();
+        let mut solver = CfrSolver::new(mask("Kh9d6c2s"), [&oop, &ip], config).unwrap();
+        let exploitability = solver.solve_until(500, 1.0, 50, |_, _| {});
+        assert!(exploitability <= 1.0, "exploitability {}", exploitability);
+        let river = match solver.tree.nodes[2].kind {
+            NodeKind::Chance { child } => child,
+            _ => panic!("check, check must deal the river"),
+        };
+        assert!(solver.strategy(river, &[]).is_err());
+        let kh: Card = "Kh".parse().unwrap();
+        assert!(solver.strategy(river, &[kh]).is_err());
+        for card in ["As", "Ah", "5h", "5c", "Ts"] {
+            let strategy = solver.strategy(river, &[card.parse().unwrap()]).unwrap();
+            assert_eq!(strategy.len(), 2);
+            for (check, bet) in strategy[0].iter().zip(&strategy[1]) {
+                assert!((check + bet - 1.0).abs() < 1e-6);
+            }
+        }
+        // a river ace gives the aces a set to value bet
+        let set = solver.strategy(river, &["Ac".parse().unwrap()]).unwrap();
+        let index = solver
+            .combos(OOP)
+            .iter()
+            .position(|c| c.mask == mask("AsAh"))
+            .unwrap();
+        assert!(set[1][index] > 0.9, "set bets {}", set[1][index]);
+    }
+
+    #[test]
+    fn flop_solve_reports_progress() {
+        let config = TreeConfig {
+            streets: vec![
+                StreetSizes::new(&[], &[]),
+                StreetSizes::new(&[], &[]),
+                StreetSizes::new(&[1.0], &[]),
+            ],
+            max_raises: 1,
+            all_in: false,
+            ..TreeConfig::default()
+        };
+        let oop = Range::from_notation("AA,JTs").unwrap();
+        let ip = Range::from_notation("KK").unwrap();
+        let mut solver = CfrSolver::new(mask("Ks8s3d"), [&oop, &ip], config).unwrap();
+        // suit swaps of the two unused suits fold turn and river cards
+        assert!(solver.runouts() < 1 + 49 + 49 * 48);
+        assert!(solver.memory_usage() > 0);
+        let mut reports = Vec::new();
+        let last = solver.solve_until(60, 0.0, 20, |i, e| reports.push((i, e)));
+        assert_eq!(
+            reports.iter().map(|r| r.0).collect::<Vec<_>>(),
+            [20, 40, 60]
+        );
+        assert_eq!(reports[2].1, last);
+        assert!(last < reports[0].1);
+        assert!(last < 2.0, "exploitability {}", last);
+    }
+
+    #[test]
+    fn best_response_exploits_uniform_play() {
+        // untrained, OOP bets half its range including four bluffs for
+        // three value combos, so IP calls everything; a half pot bluff
+        // wins the pot half the time and beats giving up
+        let mut config = pot_bet_only();
+        config.streets[0].bet = vec![0.5];
+        let solver = sets_and_air(config);
+        let facing = match &solver.tree.nodes[0].kind {
+            NodeKind::Action { children, .. } => children[1],
+            _ => unreachable!(),
+        };
+        let caller = solver.best_response(IP);
+        assert!(solver
+            .response_actions(&caller, facing, &[])
+            .unwrap()
+            .iter()
+            .all(|&a| a == 1));
+        assert!(solver.response_actions(&caller, 0, &[]).is_err());
+
+        let bettor = solver.best_response(OOP);
+        let root = solver.response_actions(&bettor, 0, &[]).unwrap();
+        for ((c, &a), (&best, &avg)) in solver
+            .combos(OOP)
+            .iter()
+            .zip(&root)
+            .zip(bettor.values.iter().zip(&solver.expected_values(OOP)))
+        {
+            if !(Hand { mask: c.mask }).to_string().contains('9') {
+                assert_eq!(a, 1, "air bets");
+            }
+            assert!(best >= avg - 1e-9);
+        }
+        let gain = bettor.value + caller.value - solver.tree.config.pot;
+        assert!((gain / 2.0 - solver.exploitability()).abs() < 1e-9);
+        assert!((solver.exploitability_mbb(2.0) - solver.exploitability() * 500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn best_response_on_turn_matches_equilibrium() {
+        let config = TreeConfig {
+            streets: vec![StreetSizes::new(&[], &[]), StreetSizes::new(&[1.0], &[])],
+            max_raises: 1,
+            all_in: false,
+            ..TreeConfig::default()
+        };
+        let oop = Range::from_notation("AA,76s").unwrap();
+        let ip = Range::from_notation("KQo").unwrap();
+        let mut solver = CfrSolver::new(mask("Kh9d6c2s"), [&oop, &ip], config).unwrap();
+        solver.solve(300);
+        let response = solver.best_response(OOP);
+        // the response gains at most what both responses gain together
+        let gain = response.value - solver.range_value(OOP, Mode::Average);
+        assert!(gain >= -1e-9);
+        assert!(gain <= 2.0 * solver.exploitability() + 1e-9);
+        let river = match solver.tree.nodes[2].kind {
+            NodeKind::Chance { child } => child,
+            _ => unreachable!(),
+        };
+        // a river ace: the set always bets
+        let aces = solver
+            .combos(OOP)
+            .iter()
+            .position(|c| c.mask == mask("AsAh"))
+            .unwrap();
+        let bets = solver
+            .response_actions(&response, river, &["Ac".parse().unwrap()])
+            .unwrap();
+        assert_eq!(bets[aces], 1);
+    }
+
+    fn facing_bet(solver: &CfrSolver) -> usize {
+        match &solver.tree.nodes[0].kind {
+            NodeKind::Action { children, .. } => children[1],
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn locked_folds_get_bluffed() {
+        // IP folds 70% to a pot sized bet, more than the 50% a bluff needs
+        let mut solver = sets_and_air(pot_bet_only());
+        let facing = facing_bet(&solver);
+        solver.lock_action(facing, 0, 0.7).unwrap();
+        solver.solve(1000);
+        let facing_strategy = solver.strategy(facing, &[]).unwrap();
+        for (fold, call) in facing_strategy[0].iter().zip(&facing_strategy[1]) {
+            assert!((fold - 0.7).abs() < 1e-9 && (call - 0.3).abs() < 1e-6);
+        }
+        let bet = &solver.strategy(0, &[]).unwrap()[1];
+        for (c, &p) in solver.combos(OOP).iter().zip(bet) {
+            assert!(p > 0.95, "{} bets {}", Hand { mask: c.mask }, p);
+        }
+        let ev = solver.expected_values(OOP);
+        let total = ev.iter().sum::<f64>() / ev.len() as f64;
+        assert!(total > 450.0 / 7.0 + 5.0, "oop value {}", total);
+        // IP's best response drops the lock and calls
+        let response = solver.best_response(IP);
+        assert!(solver
+            .response_actions(&response, facing, &[])
+            .unwrap()
+            .iter()
+            .all(|&a| a == 1));
+        assert!(solver.exploitability() > 1.0);
+
+        solver.unlock(facing);
+        solver.solve(2000);
+        let call = &solver.strategy(facing, &[]).unwrap()[1];
+        let freq = call.iter().sum::<f64>() / call.len() as f64;
+        assert!((freq - 0.5).abs() < 0.1, "call frequency {}", freq);
+    }
+
+    #[test]
+    fn best_response_to_a_population_read() {
+        // a caller known never to call gets bluffed
+        let mut solver = sets_and_air(pot_bet_only());
+        let facing = facing_bet(&solver);
+        solver.lock_action(facing, 0, 1.0).unwrap();
+        let response = solver.best_response(OOP);
+        let spots = solver.response_spots(&response);
+        assert_eq!(spots.len(), solver.tree.len());
+        assert!(spots[facing].is_empty());
+        assert_eq!(spots[0].len(), 1);
+        let (board, actions) = &spots[0][0];
+        assert_eq!(*board, mask("Kh9d6c3s2s"));
+        assert_eq!(actions.len(), solver.combos(OOP).len());
+        assert_eq!(
+            *actions,
+            solver.response_actions(&response, 0, &[]).unwrap()
+        );
+        for ((c, &a), &v) in solver.combos(OOP).iter().zip(actions).zip(&response.values) {
+            if !(Hand { mask: c.mask }).to_string().contains('9') {
+                assert_eq!(a, 1, "air bets");
+            }
+            // the whole pot, and the sets may check to win more
+            assert!(v >= 100.0 - 1e-9);
+        }
+    }
+
+    #[test]
+    fn per_combo_lock_breaks_symmetry() {
+        let config = TreeConfig {
+            streets: vec![StreetSizes::new(&[1.0], &[]), StreetSizes::new(&[], &[])],
+            max_raises: 1,
+            all_in: false,
+            ..TreeConfig::default()
+        };
+        let oop = Range::from_notation("AA").unwrap();
+        let ip = Range::from_notation("QQ").unwrap();
+        let mut solver = CfrSolver::new(mask("Ks9s6s3s"), [&oop, &ip], config).unwrap();
+        assert_eq!(solver.runouts(), 1 + 9 + 13);
+        solver.solve(10);
+        // a uniform lock keeps every symmetry and the progress made
+        solver.lock_action(0, 0, 0.5).unwrap();
+        assert_eq!((solver.runouts(), solver.iterations), (1 + 9 + 13, 10));
+
+        let bets: Vec<f64> = solver
+            .combos(OOP)
+            .iter()
+            .map(|c| if c.mask == mask("AsAh") { 1.0 } else { 0.0 })
+            .collect();
+        let checks = bets.iter().map(|b| 1.0 - b).collect();
+        solver.lock(0, &[Some(checks), Some(bets.clone())]).unwrap();
+        // only diamonds and clubs are still interchangeable
+        assert_eq!((solver.runouts(), solver.iterations), (1 + 9 + 13 + 13, 0));
+        solver.solve(50);
+        assert_eq!(solver.strategy(0, &[]).unwrap()[1], bets);
+        let river = solver
+            .tree
+            .nodes
+            .iter()
+            .position(|n| n.street == 1)
+            .unwrap();
+        assert!(solver.strategy(river, &["2h".parse().unwrap()]).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_locks() {
+        let mut solver = sets_and_air(pot_bet_only());
+        let n = solver.combos(OOP).len();
+        let terminal = solver
+            .tree
+            .nodes
+            .iter()
+            .position(|n| matches!(n.kind, NodeKind::Fold { .. }))
+            .unwrap();
+        assert!(solver.lock_action(terminal, 0, 0.5).is_err());
+        assert!(solver.lock_action(0, 2, 0.5).is_err());
+        assert!(solver.lock_action(0, 0, 1.5).is_err());
+        assert!(solver.lock(0, &[None]).is_err());
+        assert!(solver.lock(0, &[Some(vec![0.5; n - 1]), None]).is_err());
+        assert!(solver
+            .lock(0, &[Some(vec![0.7; n]), Some(vec![0.7; n])])
+            .is_err());
+        assert!(solver
+            .lock(0, &[Some(vec![0.2; n]), Some(vec![0.2; n])])
+            .is_err());
+        assert!(solver.locks.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let r = Range::from_notation("AA").unwrap();
+        assert!(CfrSolver::new(mask("Kh9d"), [&r, &r], TreeConfig::default()).is_err());
+        // a flop needs sizes for three streets
+        assert!(CfrSolver::new(mask("Kh9d6c"), [&r, &r], TreeConfig::default()).is_err());
+        let blocked = Range::from_notation("KhQh").unwrap();
+        assert!(CfrSolver::new(mask("Kh9d6c3s2s"), [&blocked, &r], TreeConfig::default()).is_err());
+    }
+}
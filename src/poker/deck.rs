@@ -0,0 +1,221 @@
+use crate::poker::range::Combo;
+use crate::poker::{Hand, Range};
+
+pub const DECK_SIZE: u32 = 52;
+
+/// Small, fast and reproducible generator; the same seed always yields the
+/// same sequence on every platform.
+#[derive(Debug, Clone)]
+pub struct XorShift64 {
+    state: u64,
+}
+
+impl XorShift64 {
+    pub fn new(seed: u64) -> Self {
+        let s = if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        };
+        Self { state: s }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    pub fn range(&mut self, n: usize) -> usize {
+        (self.next_u64() as usize) % n
+    }
+
+    /// Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Cards left to deal, with a seeded generator. Cards come off the top in
+/// order until `shuffle` is called.
+#[derive(Debug, Clone)]
+pub struct Deck {
+    cards: Vec<u8>,
+    rng: XorShift64,
+}
+
+impl Deck {
+    pub fn new(seed: u64) -> Self {
+        Self::from_top(DECK_SIZE, seed)
+    }
+
+    /// Deck holding the top `size` cards of the standard order (2s upwards
+    /// is cut away first), e.g. 36 for short-deck.
+    pub fn with_size(size: u32, seed: u64) -> Result<Self, String> {
+        if size > DECK_SIZE {
+            return Err(format!("a deck holds at most {} cards", DECK_SIZE));
+        }
+        Ok(Self::from_top(size, seed))
+    }
+
+    /// `with_size` for a `size` known to fit.
+    pub(crate) fn from_top(size: u32, seed: u64) -> Self {
+        let start = (DECK_SIZE - size) as u8;
+        Self {
+            cards: (start..DECK_SIZE as u8).rev().collect(),
+            rng: XorShift64::new(seed),
+        }
+    }
+
+    pub fn with_dead(dead: u64, seed: u64) -> Self {
+        let mut deck = Self::new(seed);
+        deck.remove(dead);
+        deck
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Remaining cards as a mask.
+    pub fn mask(&self) -> u64 {
+        self.cards.iter().fold(0, |acc, &c| acc | (1u64 << c))
+    }
+
+    pub fn contains(&self, mask: u64) -> bool {
+        self.mask() & mask == mask
+    }
+
+    /// Take dead cards out of the deck; cards already gone are ignored.
+    pub fn remove(&mut self, dead: u64) {
+        self.cards.retain(|&c| dead & (1u64 << c) == 0);
+    }
+
+    pub fn shuffle(&mut self) {
+        for i in (1..self.cards.len()).rev() {
+            let j = self.rng.range(i + 1);
+            self.cards.swap(i, j);
+        }
+    }
+
+    /// Deal `n` cards off the top.
+    pub fn deal(&mut self, n: usize) -> Result<Hand, String> {
+        if n > self.cards.len() {
+            return Err(format!(
+                "cannot deal {} cards, {} left",
+                n,
+                self.cards.len()
+            ));
+        }
+        let mut mask = 0u64;
+        for _ in 0..n {
+            mask |= 1u64 << self.cards.pop().unwrap();
+        }
+        Ok(Hand::from_mask(mask))
+    }
+
+    /// Draw a combo from `range` with probability proportional to its weight,
+    /// among combos whose cards are all still in the deck, and remove it.
+    pub fn sample_combo(&mut self, range: &Range) -> Result<Combo, String> {
+        let gone = !self.mask();
+        let total: f64 = range.live_combos(gone).map(|c| c.weight as f64).sum();
+        if total <= 0.0 {
+            return Err("range has no live combos".into());
+        }
+        let mut target = self.rng.next_f64() * total;
+        let mut chosen = None;
+        for c in range.live_combos(gone) {
+            if c.weight <= 0.0 {
+                continue;
+            }
+            chosen = Some(*c);
+            target -= c.weight as f64;
+            if target < 0.0 {
+                break;
+            }
+        }
+        let combo = chosen.unwrap();
+        self.remove(combo.mask);
+        Ok(combo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_deck() {
+        let mut deck = Deck::new(1);
+        assert_eq!(deck.len(), 52);
+        assert_eq!(deck.mask(), (1u64 << 52) - 1);
+        let top = deck.deal(2).unwrap();
+        assert_eq!(top.mask, 0b11);
+        assert_eq!(deck.len(), 50);
+    }
+
+    #[test]
+    fn dead_cards_removed() {
+        let dead = Hand::from_string("AsKd2c").mask;
+        let mut deck = Deck::with_dead(dead, 7);
+        assert_eq!(deck.len(), 49);
+        deck.shuffle();
+        let all = deck.deal(49).unwrap();
+        assert_eq!(all.mask & dead, 0);
+        assert!(deck.deal(1).is_err());
+    }
+
+    #[test]
+    fn seeded_shuffle_is_reproducible() {
+        let mut a = Deck::new(42);
+        let mut b = Deck::new(42);
+        let mut c = Deck::new(43);
+        a.shuffle();
+        b.shuffle();
+        c.shuffle();
+        let (ha, hb, hc) = (a.deal(5).unwrap(), b.deal(5).unwrap(), c.deal(5).unwrap());
+        assert_eq!(ha.mask, hb.mask);
+        assert_ne!(ha.mask, hc.mask);
+        assert_eq!(ha.len(), 5);
+    }
+
+    #[test]
+    fn short_deck_size() {
+        let deck = Deck::with_size(36, 1).unwrap();
+        assert_eq!(deck.len(), 36);
+        assert_eq!(deck.mask() & Hand::from_string("5h").mask, 0);
+        assert!(deck.contains(Hand::from_string("6s").mask));
+    }
+
+    #[test]
+    fn rejects_oversized_deck() {
+        let err = Deck::with_size(53, 1).unwrap_err();
+        assert_eq!(err, "a deck holds at most 52 cards");
+    }
+
+    #[test]
+    fn sample_respects_dead_and_weight() {
+        let range = Range::parse("AsAh,KsKh:0.5,QsQh:0").unwrap();
+        let mut deck = Deck::with_dead(Hand::from_string("Ad").mask, 3);
+        let mut kings = 0;
+        for _ in 0..2000 {
+            let mut d = deck.clone();
+            let c = d.sample_combo(&range).unwrap();
+            assert!(!d.contains(c.mask));
+            if c.mask == Hand::from_string("KsKh").mask {
+                kings += 1;
+            }
+            deck.shuffle();
+        }
+        assert!((500..833).contains(&kings), "{}", kings);
+        deck.remove(Hand::from_string("AsKs").mask);
+        assert!(deck.sample_combo(&range).is_err());
+    }
+}
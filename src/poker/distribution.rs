@@ -0,0 +1,444 @@
+use crate::poker::deck::XorShift64;
+use crate::poker::evaluator::eval;
+use crate::poker::game::{binom, pick, pick_random_board, DEFAULT_MC_ITERATIONS, EXACT_WORK_LIMIT};
+use crate::poker::Hand;
+use crate::poker::Range;
+use std::collections::HashMap;
+
+/// Weight of villain combos a hero combo beats, ties and could face on one
+/// complete board, card removal included.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct Matchup {
+    pub win: f64,
+    pub tie: f64,
+    pub total: f64,
+}
+
+pub(crate) fn cards_of(mask: u64) -> (usize, usize) {
+    (
+        mask.trailing_zeros() as usize,
+        63 - mask.leading_zeros() as usize,
+    )
+}
+
+/// Scores of one side's combos on a complete board, with the combos sorted
+/// by score. Combos clashing with the board score 0.
+#[derive(Debug, Clone)]
+pub(crate) struct Ranking {
+    score: Vec<u32>,
+    order: Vec<u32>,
+}
+
+impl Ranking {
+    pub fn new(masks: &[u64], board: u64) -> Self {
+        Self::from_scores(
+            masks
+                .iter()
+                .map(|&m| if m & board == 0 { eval(m | board) } else { 0 })
+                .collect(),
+        )
+    }
+
+    pub fn from_scores(score: Vec<u32>) -> Self {
+        let mut order: Vec<u32> = (0..score.len() as u32).collect();
+        order.sort_by_key(|&i| score[i as usize]);
+        Self { score, order }
+    }
+}
+
+/// Showdowns of 2-card `hero` masks against 2-card `villain` masks. The
+/// card removal bookkeeping is shared by every board; each `matchups` call
+/// runs in linear time for any villain weights, with both sides ranked on
+/// the board at hand.
+#[derive(Debug, Clone)]
+pub(crate) struct Showdown {
+    hero: Vec<u64>,
+    villain: Vec<u64>,
+    /// Villain combo identical to each hero combo.
+    same: Vec<Option<usize>>,
+}
+
+impl Showdown {
+    pub fn new(hero: &[u64], villain: &[u64]) -> Self {
+        let index: HashMap<u64, usize> = villain.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+        Self {
+            same: hero.iter().map(|h| index.get(h).copied()).collect(),
+            hero: hero.to_vec(),
+            villain: villain.to_vec(),
+        }
+    }
+
+    /// Villain weight each hero combo can face, card removal included.
+    pub fn compatible(&self, weights: &[f64]) -> Vec<f64> {
+        let mut card = [0.0f64; 52];
+        let mut total = 0.0;
+        for (&v, &w) in self.villain.iter().zip(weights) {
+            let (a, b) = cards_of(v);
+            card[a] += w;
+            card[b] += w;
+            total += w;
+        }
+        self.hero
+            .iter()
+            .zip(&self.same)
+            .map(|(&h, same)| {
+                let (a, b) = cards_of(h);
+                total - card[a] - card[b] + same.map_or(0.0, |i| weights[i])
+            })
+            .collect()
+    }
+
+    /// Villain weight each hero combo beats, ties and faces.
+    pub fn matchups(&self, hero: &Ranking, villain: &Ranking, weights: &[f64]) -> Vec<Matchup> {
+        let totals = self.compatible(weights);
+        let mut out = vec![Matchup::default(); self.hero.len()];
+        let (mut lt, mut le) = (0usize, 0usize);
+        let (mut lt_total, mut le_total) = (0.0, 0.0);
+        let mut lt_card = [0.0f64; 52];
+        let mut le_card = [0.0f64; 52];
+        let sorted = &villain.order;
+        for &i in &hero.order {
+            let i = i as usize;
+            let score = hero.score[i];
+            while lt < sorted.len() && villain.score[sorted[lt] as usize] < score {
+                let v = sorted[lt] as usize;
+                let (a, b) = cards_of(self.villain[v]);
+                lt_card[a] += weights[v];
+                lt_card[b] += weights[v];
+                lt_total += weights[v];
+                lt += 1;
+            }
+            while le < sorted.len() && villain.score[sorted[le] as usize] <= score {
+                let v = sorted[le] as usize;
+                let (a, b) = cards_of(self.villain[v]);
+                le_card[a] += weights[v];
+                le_card[b] += weights[v];
+                le_total += weights[v];
+                le += 1;
+            }
+            let (a, b) = cards_of(self.hero[i]);
+            // the identical combo was subtracted twice and always ties
+            let back = self.same[i].map_or(0.0, |v| weights[v]);
+            let win = lt_total - lt_card[a] - lt_card[b];
+            let le_live = le_total - le_card[a] - le_card[b] + back;
+            out[i] = Matchup {
+                win,
+                tie: le_live - win,
+                total: totals[i],
+            };
+        }
+        out
+    }
+}
+
+/// Showdown of every 2-card `hero` mask against weighted `villain` masks,
+/// each list given with its scores on the same board.
+pub(crate) fn matchups(hero: &[(u64, u32)], villain: &[(u64, u32, f64)]) -> Vec<Matchup> {
+    let showdown = Showdown::new(
+        &hero.iter().map(|h| h.0).collect::<Vec<_>>(),
+        &villain.iter().map(|v| v.0).collect::<Vec<_>>(),
+    );
+    let weights: Vec<f64> = villain.iter().map(|v| v.2).collect();
+    showdown.matchups(
+        &Ranking::from_scores(hero.iter().map(|h| h.1).collect()),
+        &Ranking::from_scores(villain.iter().map(|v| v.1).collect()),
+        &weights,
+    )
+}
+
+/// Equity of one combo of a range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComboEquity {
+    pub mask: u64,
+    pub weight: f32,
+    pub equity: f64,
+}
+
+/// Per-combo equities of one range against another, sorted weakest first.
+#[derive(Debug, Default, Clone)]
+pub struct EquityDistribution {
+    /// Runouts looked at.
+    pub iterations: u64,
+    pub combos: Vec<ComboEquity>,
+}
+
+impl EquityDistribution {
+    fn total_weight(&self) -> f64 {
+        self.combos.iter().map(|c| c.weight as f64).sum()
+    }
+
+    /// Weighted average equity of the range.
+    pub fn equity(&self) -> f64 {
+        let total = self.total_weight();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let sum: f64 = self.combos.iter().map(|c| c.weight as f64 * c.equity).sum();
+        sum / total
+    }
+
+    /// Share of the range's weight in each of `buckets` equal equity bins
+    /// over [0, 1].
+    pub fn histogram(&self, buckets: usize) -> Vec<f64> {
+        let mut out = vec![0.0; buckets.max(1)];
+        let total = self.total_weight();
+        if total <= 0.0 {
+            return out;
+        }
+        let last = out.len() - 1;
+        for c in &self.combos {
+            let i = ((c.equity * out.len() as f64) as usize).min(last);
+            out[i] += c.weight as f64 / total;
+        }
+        out
+    }
+
+    /// Equity curve: `(fraction of the range at or below, equity)` per combo,
+    /// weakest first.
+    pub fn curve(&self) -> Vec<(f64, f64)> {
+        let total = self.total_weight();
+        let mut seen = 0.0;
+        self.combos
+            .iter()
+            .map(|c| {
+                seen += c.weight as f64;
+                (seen / total, c.equity)
+            })
+            .collect()
+    }
+
+    /// ASCII histogram, one line per bucket with a bar `width` characters at
+    /// the fullest bucket.
+    pub fn render(&self, buckets: usize, width: usize) -> String {
+        let hist = self.histogram(buckets);
+        let top = hist.iter().cloned().fold(0.0, f64::max);
+        let mut out = String::new();
+        for (i, share) in hist.iter().enumerate() {
+            let lo = i * 100 / hist.len();
+            let hi = (i + 1) * 100 / hist.len();
+            let bar = if top > 0.0 {
+                (share / top * width as f64).round() as usize
+            } else {
+                0
+            };
+            out.push_str(&format!(
+                "{:>3}-{:<3}% |{:<width$}| {:>5.1}%\n",
+                lo,
+                hi,
+                "#".repeat(bar),
+                share * 100.0,
+                width = width
+            ));
+        }
+        out
+    }
+
+    /// CSV of `combo,weight,equity` rows, weakest first.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("combo,weight,equity\n");
+        for c in &self.combos {
+            out.push_str(&format!(
+                "{},{},{}\n",
+                Hand { mask: c.mask },
+                c.weight,
+                c.equity
+            ));
+        }
+        out
+    }
+}
+
+fn live_scored(range: &Range, dead: u64) -> Vec<(u64, f32)> {
+    range
+        .live_combos(dead)
+        .filter(|c| c.weight > 0.0)
+        .map(|c| (c.mask, c.weight))
+        .collect()
+}
+
+fn collect(list: &[(u64, f32)], sums: &[(f64, f64)]) -> Vec<ComboEquity> {
+    let mut out: Vec<ComboEquity> = list
+        .iter()
+        .zip(sums)
+        .filter(|(_, s)| s.1 > 0.0)
+        .map(|(&(mask, weight), s)| ComboEquity {
+            mask,
+            weight,
+            equity: s.0 / s.1,
+        })
+        .collect();
+    out.sort_by(|a, b| a.equity.total_cmp(&b.equity).then(a.mask.cmp(&b.mask)));
+    out
+}
+
+/// Equity distributions of `hero` against `villain` and of `villain`
+/// against `hero` on `community` (0 to 5 cards). Runouts are enumerated
+/// when cheap and sampled otherwise (`max_iterations` of them, 0 for the
+/// default).
+pub fn equity_distributions(
+    hero: &Range,
+    villain: &Range,
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+) -> Result<(EquityDistribution, EquityDistribution), String> {
+    let count = community.count_ones();
+    if count > 5 {
+        return Err("community must have at most 5 cards".into());
+    }
+    let sides = [
+        live_scored(hero, community),
+        live_scored(villain, community),
+    ];
+    for side in &sides {
+        if side.is_empty() {
+            return Err("range has no live combos".into());
+        }
+        if side.iter().any(|c| c.0.count_ones() != 2) {
+            return Err("ranges must hold 2 card combos".into());
+        }
+    }
+    let mut sums = [
+        vec![(0.0f64, 0.0f64); sides[0].len()],
+        vec![(0.0f64, 0.0f64); sides[1].len()],
+    ];
+    let mut runouts = 0u64;
+    let mut visit = |add: u64| {
+        let board = community | add;
+        let scored: Vec<Vec<(u64, u32, f64)>> = sides
+            .iter()
+            .map(|side| {
+                side.iter()
+                    .map(|&(m, w)| {
+                        let score = if m & add == 0 { eval(m | board) } else { 0 };
+                        (m, score, if m & add == 0 { w as f64 } else { 0.0 })
+                    })
+                    .collect()
+            })
+            .collect();
+        for me in 0..2 {
+            let mine: Vec<(u64, u32)> = scored[me].iter().map(|c| (c.0, c.1)).collect();
+            for (i, m) in matchups(&mine, &scored[1 - me]).iter().enumerate() {
+                if scored[me][i].2 > 0.0 {
+                    sums[me][i].0 += m.win + m.tie / 2.0;
+                    sums[me][i].1 += m.total;
+                }
+            }
+        }
+        runouts += 1;
+    };
+
+    let need = 5 - count;
+    let size = (sides[0].len() + sides[1].len()) as u128;
+    if binom(52 - count, need).saturating_mul(size) <= EXACT_WORK_LIMIT {
+        pick(community, need, 0, 0, &mut visit);
+    } else {
+        let target = if max_iterations == 0 {
+            DEFAULT_MC_ITERATIONS / 100
+        } else {
+            max_iterations
+        };
+        let mut rng = XorShift64::new(seed);
+        for _ in 0..target {
+            visit(pick_random_board(community, need, &mut rng));
+        }
+    }
+
+    let hero = EquityDistribution {
+        iterations: runouts,
+        combos: collect(&sides[0], &sums[0]),
+    };
+    let villain = EquityDistribution {
+        iterations: runouts,
+        combos: collect(&sides[1], &sums[1]),
+    };
+    if hero.combos.is_empty() || villain.combos.is_empty() {
+        return Err("ranges never meet at showdown".into());
+    }
+    Ok((hero, villain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::solve_ranges;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    #[test]
+    fn matchups_remove_blocked_combos() {
+        let board = mask("2c3d7h8sTs");
+        let villain: Vec<(u64, u32, f64)> = Range::any()
+            .live_combos(board)
+            .map(|c| (c.mask, eval(c.mask | board), 1.0))
+            .collect();
+        let hero = [(mask("AsAh"), eval(mask("AsAh") | board))];
+        let m = matchups(&hero, &villain)[0];
+        let mut expect = Matchup::default();
+        for &(v, score, _) in &villain {
+            if v & hero[0].0 != 0 {
+                continue;
+            }
+            expect.total += 1.0;
+            if score < hero[0].1 {
+                expect.win += 1.0;
+            } else if score == hero[0].1 {
+                expect.tie += 1.0;
+            }
+        }
+        assert_eq!(m, expect);
+        assert_eq!(m.total, 45.0 * 44.0 / 2.0);
+    }
+
+    #[test]
+    fn matches_combo_equity() {
+        let board = mask("Kd9s4c2h");
+        let hero = Range::from_notation("AA,76s").unwrap();
+        let villain = Range::from_notation("KQ,99").unwrap();
+        let (h, v) = equity_distributions(&hero, &villain, board, 0, 1).unwrap();
+        assert_eq!(h.iterations, 48);
+        for c in &h.combos {
+            let r = solve_ranges(c.mask, std::slice::from_ref(&villain), board, 0, 1).unwrap();
+            let expect = r.hero_win + r.hero_tie / 2.0;
+            assert!((c.equity - expect).abs() < 1e-9);
+        }
+        assert_eq!(h.combos.len(), 6 + 4);
+        assert_eq!(v.combos.len(), 12 + 3);
+        assert!(h.equity() < 0.5 && v.equity() > 0.5);
+    }
+
+    #[test]
+    fn histogram_and_curve() {
+        let board = mask("AhKh2c7d");
+        let hero = Range::from_notation("AA,QhJh,33").unwrap();
+        let villain = Range::from_notation("AK").unwrap();
+        let (h, _) = equity_distributions(&hero, &villain, board, 0, 1).unwrap();
+        let hist = h.histogram(10);
+        assert!((hist.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(hist[9] > 0.0 && hist[0] > 0.0);
+        let curve = h.curve();
+        assert_eq!(curve.last().unwrap().0, 1.0);
+        assert!(curve.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(h.render(10, 20).lines().count(), 10);
+        assert_eq!(h.to_csv().lines().count(), h.combos.len() + 1);
+    }
+
+    #[test]
+    fn preflop_samples() {
+        let hero = Range::from_notation("AA").unwrap();
+        let villain = Range::from_notation("KK").unwrap();
+        let (h, v) = equity_distributions(&hero, &villain, 0, 200, 3).unwrap();
+        assert_eq!(h.iterations, 200);
+        assert!(h.equity() > 0.7 && v.equity() < 0.3);
+    }
+
+    #[test]
+    fn rejects_dead_ranges() {
+        let board = mask("AsAhAd");
+        let hero = Range::from_notation("AA").unwrap();
+        let villain = Range::from_notation("KK").unwrap();
+        assert!(equity_distributions(&hero, &villain, board, 0, 1).is_err());
+    }
+}
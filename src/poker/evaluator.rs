@@ -3,7 +3,7 @@ use std::cmp::Ordering;
 
 const RANK_COUNT: u32 = 13;
 
-fn pack_suit(mask: u64, s: u32) -> u16 {
+pub(crate) fn pack_suit(mask: u64, s: u32) -> u16 {
     let mut r = 0u16;
     for i in 0..RANK_COUNT {
         if (mask >> (i * 4 + s)) & 1 != 0 {
@@ -13,7 +13,7 @@ fn pack_suit(mask: u64, s: u32) -> u16 {
     r
 }
 
-fn straight_top(rmask: u16) -> i32 {
+pub(crate) fn straight_top(rmask: u16) -> i32 {
     let s = rmask & (rmask >> 1) & (rmask >> 2) & (rmask >> 3) & (rmask >> 4);
     if s != 0 {
         return 15 - s.leading_zeros() as i32 + 4;
@@ -46,6 +46,13 @@ fn top_n_excl(rmask: u16, exclude: u16, n: u32) -> u32 {
 }
 
 pub fn eval(mask: u64) -> u32 {
+    eval_with(mask, straight_top, false)
+}
+
+/// `eval` with a pluggable straight detector and, for short-deck, flushes
+/// checked ahead of full houses. Scores keep the standard category codes.
+#[inline(always)]
+pub(crate) fn eval_with<F: Fn(u16) -> i32>(mask: u64, straight: F, flush_first: bool) -> u32 {
     let s0 = pack_suit(mask, 0);
     let s1 = pack_suit(mask, 1);
     let s2 = pack_suit(mask, 2);
@@ -65,7 +72,7 @@ pub fn eval(mask: u64) -> u32 {
     };
 
     if flush_mask != 0 {
-        let sf = straight_top(flush_mask);
+        let sf = straight(flush_mask);
         if sf >= 0 {
             return (8u32 << 24) | sf as u32;
         }
@@ -100,6 +107,10 @@ pub fn eval(mask: u64) -> u32 {
         return (7u32 << 24) | ((quad as u32) << 4) | kicker;
     }
 
+    if flush_first && flush_mask != 0 {
+        return (5u32 << 24) | top_n(flush_mask, 5);
+    }
+
     if trips[0] >= 0 {
         let mut pair_rank: i32 = -1;
         if trips[1] >= 0 {
@@ -117,7 +128,7 @@ pub fn eval(mask: u64) -> u32 {
         return (5u32 << 24) | top_n(flush_mask, 5);
     }
 
-    let st = straight_top(rmask);
+    let st = straight(rmask);
     if st >= 0 {
         return (4u32 << 24) | st as u32;
     }
@@ -141,6 +152,24 @@ pub fn eval(mask: u64) -> u32 {
     top_n(rmask, 5)
 }
 
+pub const CATEGORY_NAMES: [&str; 9] = [
+    "High card",
+    "Pair",
+    "Two pair",
+    "Three of a kind",
+    "Straight",
+    "Flush",
+    "Full house",
+    "Four of a kind",
+    "Straight flush",
+];
+
+/// Category of an `eval` score, 0 (high card) to 8 (straight flush). Variant
+/// evaluators keep the category in the same bits.
+pub fn category(score: u32) -> usize {
+    ((score >> 24) & 0xF) as usize
+}
+
 #[derive(Default)]
 pub struct Evaluator;
 
@@ -0,0 +1,307 @@
+use crate::poker::grid::{class_combos, class_label, GRID_SIZE};
+use crate::poker::range::{parse_token, Combo};
+use crate::poker::{Hand, Range};
+use std::fs;
+use std::path::Path;
+
+/// Text layouts other poker tools use to exchange weighted ranges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeFormat {
+    /// `AA,KK:0.5,AKs:0.25`, weights as fractions after a colon.
+    Weighted,
+    /// One `AhKh: 0.75` combo per line.
+    ComboLines,
+    /// `QQ+,[75]AKs,AQs[/75]`, weights as percentages around groups.
+    Bracketed,
+}
+
+impl RangeFormat {
+    pub fn detect(s: &str) -> Self {
+        if s.contains('[') {
+            return RangeFormat::Bracketed;
+        }
+        let lines = s.lines().filter(|l| !l.trim().is_empty()).count();
+        if lines > 1 && !s.contains(',') {
+            return RangeFormat::ComboLines;
+        }
+        RangeFormat::Weighted
+    }
+}
+
+fn parse_weight(s: &str) -> Result<f32, String> {
+    let w: f32 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("bad weight: {}", s.trim()))?;
+    if !(0.0..=1.0).contains(&w) {
+        return Err(format!("weight out of [0, 1]: {}", w));
+    }
+    Ok(w)
+}
+
+fn push_weighted(out: &mut Vec<Combo>, token: &str, weight: f32) -> Result<(), String> {
+    let (head, weight) = match token.split_once(':') {
+        Some((head, w)) => (head, parse_weight(w)? * weight),
+        None => (token, weight),
+    };
+    for c in parse_token(head)? {
+        out.push(Combo::with_weight(c.mask, weight));
+    }
+    Ok(())
+}
+
+fn parse_weighted(s: &str) -> Result<Vec<Combo>, String> {
+    let mut out = Vec::new();
+    for tok in s.split([',', '\n']) {
+        push_weighted(&mut out, tok, 1.0)?;
+    }
+    Ok(out)
+}
+
+fn parse_bracketed(s: &str) -> Result<Vec<Combo>, String> {
+    let mut out = Vec::new();
+    let mut weight = 1.0f32;
+    let mut token = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                push_weighted(&mut out, &token, weight)?;
+                token.clear();
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if tag.starts_with('/') {
+                    weight = 1.0;
+                } else {
+                    let pct: f32 = tag
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("bad bracket weight: {}", tag))?;
+                    weight = parse_weight(&(pct / 100.0).to_string())?;
+                }
+            }
+            ',' | '\n' => {
+                push_weighted(&mut out, &token, weight)?;
+                token.clear();
+            }
+            _ => token.push(c),
+        }
+    }
+    push_weighted(&mut out, &token, weight)?;
+    Ok(out)
+}
+
+fn normalize(mut combos: Vec<Combo>) -> Range {
+    combos.sort_by_key(|c| c.mask);
+    combos.dedup_by(|later, earlier| {
+        if later.mask == earlier.mask {
+            earlier.weight = later.weight;
+            true
+        } else {
+            false
+        }
+    });
+    combos.retain(|c| c.weight > 0.0);
+    Range { combos }
+}
+
+fn format_number(x: f32) -> String {
+    let s = format!("{:.4}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    s.to_string()
+}
+
+fn combo_label(mask: u64) -> String {
+    Hand::from_mask(mask).to_string()
+}
+
+impl Range {
+    /// Parse any supported layout, detecting it from the text. Plain
+    /// notation is the weighted layout with every weight left out.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::parse_as(s, RangeFormat::detect(s))
+    }
+
+    /// Parse with an explicit layout. Later entries override earlier ones
+    /// for the same combo; zero weight combos are dropped.
+    pub fn parse_as(s: &str, format: RangeFormat) -> Result<Self, String> {
+        let combos = match format {
+            RangeFormat::Weighted | RangeFormat::ComboLines => parse_weighted(s)?,
+            RangeFormat::Bracketed => parse_bracketed(s)?,
+        };
+        Ok(normalize(combos))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(&text)
+    }
+
+    /// Parse a command line argument: `@path` loads a file, anything else
+    /// is parsed as range text.
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        match arg.strip_prefix('@') {
+            Some(path) => Self::from_file(path),
+            None => Self::parse(arg),
+        }
+    }
+
+    /// Range as (label, weight) entries. Classes where every combo shares a
+    /// weight collapse to their class label, others list combos one by one.
+    fn weighted_entries(&self) -> Vec<(String, f32)> {
+        let grid = self.to_grid();
+        let mut out = Vec::new();
+        for row in 0..GRID_SIZE {
+            for col in 0..GRID_SIZE {
+                let cell = grid.cell(row, col);
+                if cell.present() == 0 {
+                    continue;
+                }
+                if !cell.is_partial() {
+                    out.push((class_label(row, col), cell.weights[0]));
+                    continue;
+                }
+                for (c, &w) in class_combos(row, col).iter().zip(cell.weights()) {
+                    if w > 0.0 {
+                        out.push((combo_label(c.mask), w));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn export(&self, format: RangeFormat) -> String {
+        let entries = self.weighted_entries();
+        match format {
+            RangeFormat::Weighted => entries
+                .iter()
+                .map(|(label, w)| {
+                    if *w == 1.0 {
+                        label.clone()
+                    } else {
+                        format!("{}:{}", label, format_number(*w))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            RangeFormat::ComboLines => {
+                let mut out = String::new();
+                for c in self.combos.iter() {
+                    out.push_str(&format!(
+                        "{}: {}\n",
+                        combo_label(c.mask),
+                        format_number(c.weight)
+                    ));
+                }
+                out
+            }
+            RangeFormat::Bracketed => {
+                let mut groups: Vec<(f32, Vec<String>)> = Vec::new();
+                for (label, w) in entries {
+                    match groups.iter_mut().find(|(gw, _)| *gw == w) {
+                        Some((_, labels)) => labels.push(label),
+                        None => groups.push((w, vec![label])),
+                    }
+                }
+                groups
+                    .iter()
+                    .map(|(w, labels)| {
+                        if *w == 1.0 {
+                            labels.join(",")
+                        } else {
+                            let pct = format_number(w * 100.0);
+                            format!("[{}]{}[/{}]", pct, labels.join(","), pct)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight_of(r: &Range, combo: &str) -> Option<f32> {
+        let m = Hand::from_string(combo).mask;
+        r.combos.iter().find(|c| c.mask == m).map(|c| c.weight)
+    }
+
+    #[test]
+    fn colon_weights() {
+        let r = Range::parse("AA:1.0,AKs:0.5,QQ").unwrap();
+        assert_eq!(r.len(), 6 + 4 + 6);
+        assert_eq!(weight_of(&r, "AsKs"), Some(0.5));
+        assert_eq!(weight_of(&r, "QsQd"), Some(1.0));
+    }
+
+    #[test]
+    fn combo_lines() {
+        let text = "AhKh: 0.75\nAsKs: 0.25\n";
+        assert_eq!(RangeFormat::detect(text), RangeFormat::ComboLines);
+        let r = Range::parse(text).unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(weight_of(&r, "AhKh"), Some(0.75));
+    }
+
+    #[test]
+    fn bracket_groups() {
+        let text = "QQ+,[75]AKs,AQs[/75],[50.0]JJ[/50.0]";
+        assert_eq!(RangeFormat::detect(text), RangeFormat::Bracketed);
+        let r = Range::parse(text).unwrap();
+        assert_eq!(r.len(), 18 + 8 + 6);
+        assert_eq!(weight_of(&r, "AdQd"), Some(0.75));
+        assert_eq!(weight_of(&r, "JsJh"), Some(0.5));
+        assert_eq!(weight_of(&r, "KsKh"), Some(1.0));
+    }
+
+    #[test]
+    fn later_entry_overrides() {
+        let r = Range::parse("AA:0.5,AsAh:1,KK:0").unwrap();
+        assert_eq!(r.len(), 6);
+        assert_eq!(weight_of(&r, "AsAh"), Some(1.0));
+        assert_eq!(weight_of(&r, "AsAd"), Some(0.5));
+    }
+
+    #[test]
+    fn rejects_bad_weight() {
+        assert!(Range::parse("AA:1.5").is_err());
+        assert!(Range::parse("AA:x").is_err());
+        assert!(Range::parse("[abc]AA[/abc]").is_err());
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let r = Range::parse("AA,KK:0.5,AsKs:0.25,AKo:0.75").unwrap();
+        for format in [
+            RangeFormat::Weighted,
+            RangeFormat::ComboLines,
+            RangeFormat::Bracketed,
+        ] {
+            let text = r.export(format);
+            let back = Range::parse_as(&text, format).unwrap();
+            assert_eq!(back.combos, r.combos, "{:?}: {}", format, text);
+        }
+    }
+
+    #[test]
+    fn export_compresses_classes() {
+        let r = Range::parse("AA,KK:0.5,AsKs").unwrap();
+        assert_eq!(r.export(RangeFormat::Weighted), "AA,AsKs,KK:0.5");
+        assert_eq!(r.export(RangeFormat::Bracketed), "AA,AsKs,[50]KK[/50]");
+    }
+
+    #[test]
+    fn file_argument() {
+        let path = std::env::temp_dir().join("poker_solver_range_arg.txt");
+        fs::write(&path, "AhKh: 0.75\nQsQd: 1\n").unwrap();
+        let r = Range::from_arg(&format!("@{}", path.display())).unwrap();
+        assert_eq!(r.len(), 2);
+        assert!(Range::from_arg("@/no/such/file").is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
-use crate::poker::evaluator::eval;
+use crate::poker::deck::XorShift64;
+use crate::poker::rules::{GameRules, Holdem, FULL_DECK};
 use crate::poker::Hand;
 use crate::poker::Range;
 
@@ -79,7 +80,7 @@ fn parse_player(s: &str) -> Result<Range, String> {
     Range::from_notation(s)
 }
 
-fn pick<F: FnMut(u64)>(used: u64, k: u32, start: u32, picked: u64, f: &mut F) {
+pub(crate) fn pick<F: FnMut(u64)>(used: u64, k: u32, start: u32, picked: u64, f: &mut F) {
     if k == 0 {
         f(picked);
         return;
@@ -94,7 +95,7 @@ fn pick<F: FnMut(u64)>(used: u64, k: u32, start: u32, picked: u64, f: &mut F) {
     }
 }
 
-fn binom(n: u32, k: u32) -> u128 {
+pub(crate) fn binom(n: u32, k: u32) -> u128 {
     if k > n {
         return 0;
     }
@@ -117,61 +118,30 @@ fn estimate_work(villain_live: &[u128], free_after_villains: u32, need_c: u32) -
     prod.saturating_mul(binom(free_after_villains, need_c))
 }
 
-fn tally_outcome(
-    scores: &[u32],
+/// Credit one showdown, given each player's fraction of the pot. Hero wins
+/// when taking the whole pot, ties when taking part of it.
+pub(crate) fn tally_outcome(
+    shares: &[f64],
     acc: &mut [f64],
     hero_win: &mut f64,
     hero_tie: &mut f64,
     hero_lose: &mut f64,
     weight: f64,
 ) {
-    let max = *scores.iter().max().unwrap();
-    let winners = scores.iter().filter(|&&s| s == max).count() as f64;
-    let share = weight / winners;
-    for (i, &s) in scores.iter().enumerate() {
-        if s == max {
-            acc[i] += share;
-        }
-    }
-    let hero = scores[0];
-    if hero == max {
-        if winners > 1.5 {
-            *hero_tie += weight;
-        } else {
-            *hero_win += weight;
-        }
+    for (a, &s) in acc.iter_mut().zip(shares) {
+        *a += s * weight;
+    }
+    let hero = shares[0];
+    if hero >= 1.0 {
+        *hero_win += weight;
+    } else if hero > 0.0 {
+        *hero_tie += weight;
     } else {
         *hero_lose += weight;
     }
 }
 
-struct XorShift64 {
-    state: u64,
-}
-
-impl XorShift64 {
-    fn new(seed: u64) -> Self {
-        let s = if seed == 0 {
-            0x9E37_79B9_7F4A_7C15
-        } else {
-            seed
-        };
-        Self { state: s }
-    }
-    fn next(&mut self) -> u64 {
-        let mut x = self.state;
-        x ^= x << 13;
-        x ^= x >> 7;
-        x ^= x << 17;
-        self.state = x;
-        x
-    }
-    fn range(&mut self, n: usize) -> usize {
-        (self.next() as usize) % n
-    }
-}
-
-fn pick_random_board(used: u64, need: u32, rng: &mut XorShift64) -> u64 {
+pub(crate) fn pick_random_board(used: u64, need: u32, rng: &mut XorShift64) -> u64 {
     let mut free: Vec<u8> = (0..52u8).filter(|&i| used & (1u64 << i) == 0).collect();
     let mut out = 0u64;
     for _ in 0..need {
@@ -192,106 +162,236 @@ pub fn solve_ranges(
     if hero.count_ones() != 2 {
         return Err("hero must have exactly 2 cards".into());
     }
+    if !(3..=5).contains(&community.count_ones()) {
+        return Err("community must have 3-5 cards".into());
+    }
+    solve_equity(&Holdem, hero, villains, community, max_iterations, seed)
+}
+
+/// Equity engine shared by every variant: exact enumeration when cheap,
+/// Monte Carlo otherwise, pot shares decided by `rules`. `community` is
+/// empty preflop or holds a flop or more; a partial flop is rejected.
+pub fn solve_equity<R: GameRules + ?Sized>(
+    rules: &R,
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+) -> Result<EquityResult, String> {
+    equity_within(
+        rules,
+        hero,
+        villains,
+        community,
+        max_iterations,
+        seed,
+        EXACT_WORK_LIMIT,
+    )
+}
+
+/// `solve_equity` by Monte Carlo alone, `iterations` deals even when
+/// enumerating would be cheap; for callers running many small matchups.
+pub(crate) fn sample_equity<R: GameRules + ?Sized>(
+    rules: &R,
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    iterations: u64,
+    seed: u64,
+) -> Result<EquityResult, String> {
+    equity_within(rules, hero, villains, community, iterations, seed, 0)
+}
+
+fn equity_within<R: GameRules + ?Sized>(
+    rules: &R,
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+    exact_limit: u128,
+) -> Result<EquityResult, String> {
+    let n = villains.len();
+    let mut acc = vec![0.0f64; n + 1];
+    let mut hero_win = 0.0;
+    let mut hero_tie = 0.0;
+    let mut hero_lose = 0.0;
+    let mut iters: u64 = 0;
+    let mut total = 0.0;
+    let mut shares = vec![0.0f64; n + 1];
+    deal_out_within(
+        rules,
+        hero,
+        villains,
+        community,
+        max_iterations,
+        seed,
+        exact_limit,
+        |holes, board, weight| {
+            rules.split(holes, board, &mut shares);
+            tally_outcome(
+                &shares,
+                &mut acc,
+                &mut hero_win,
+                &mut hero_tie,
+                &mut hero_lose,
+                weight,
+            );
+            iters += 1;
+            total += weight;
+        },
+    )?;
+    finalize(iters, total, acc, hero_win, hero_tie, hero_lose)
+}
+
+/// Deals every showdown the equity engines score: exhaustively when the
+/// work fits `EXACT_WORK_LIMIT`, by Monte Carlo otherwise. `visit` gets the
+/// hole cards of each player (hero first), the full board and the product
+/// of the villain combo weights.
+pub(crate) fn deal_out<R: GameRules + ?Sized, V: FnMut(&[u64], u64, f64)>(
+    rules: &R,
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+    visit: V,
+) -> Result<(), String> {
+    deal_out_within(
+        rules,
+        hero,
+        villains,
+        community,
+        max_iterations,
+        seed,
+        EXACT_WORK_LIMIT,
+        visit,
+    )
+}
+
+/// `deal_out` enumerating only when the work fits `exact_limit`.
+#[allow(clippy::too_many_arguments)]
+fn deal_out_within<R: GameRules + ?Sized, V: FnMut(&[u64], u64, f64)>(
+    rules: &R,
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+    exact_limit: u128,
+    mut visit: V,
+) -> Result<(), String> {
+    let hole = hero.count_ones();
+    let holes = rules.hole_cards();
+    if !holes.contains(&hole) {
+        return Err(if holes.start() == holes.end() {
+            format!("hero must have exactly {} cards", holes.start())
+        } else {
+            format!("hero must have {}-{} cards", holes.start(), holes.end())
+        });
+    }
     if community & hero != 0 {
         return Err("community overlaps hero".into());
     }
+    let board_size = rules.board_size();
     let community_count = community.count_ones();
-    if !(3..=5).contains(&community_count) {
-        return Err("community must have 3-5 cards".into());
+    if community_count > board_size {
+        return Err(format!("community must have at most {} cards", board_size));
+    }
+    if (1..3).contains(&community_count) {
+        return Err("community must be empty or have at least 3 cards".into());
     }
     if villains.is_empty() {
         return Err("need at least one villain".into());
     }
 
-    let need_c = 5 - community_count;
-    let dead0 = hero | community;
+    let dead = FULL_DECK & !rules.deck();
+    if dead & (hero | community) != 0 {
+        return Err("hero or community uses a card outside the deck".into());
+    }
+    let need_c = board_size - community_count;
+    let dead0 = hero | community | dead;
 
     let mut live_counts: Vec<u128> = Vec::with_capacity(villains.len());
     for v in villains {
-        let n = v.live_combos(dead0).count() as u128;
+        if v.combos.iter().any(|c| c.mask.count_ones() != hole) {
+            return Err(format!("villain combos must have {} cards like hero", hole));
+        }
+        let n = v.live_combos(dead0).filter(|c| c.weight > 0.0).count() as u128;
         if n == 0 {
             return Err("villain range has no live combos".into());
         }
         live_counts.push(n);
     }
-    let free_after = 52u32 - dead0.count_ones() - 2 * villains.len() as u32;
-    let work = estimate_work(&live_counts, free_after, need_c);
+    let free_after = 52u32.saturating_sub(dead0.count_ones() + hole * villains.len() as u32);
+    let work = estimate_work(&live_counts, free_after, need_c).saturating_mul(rules.cost(hole));
 
-    if work <= EXACT_WORK_LIMIT {
-        solve_exact(hero, villains, community, dead0, need_c)
+    if work <= exact_limit {
+        deal_exact(hero, villains, community, dead0, need_c, &mut visit);
+        Ok(())
     } else {
         let iters = if max_iterations == 0 {
             DEFAULT_MC_ITERATIONS
         } else {
             max_iterations
         };
-        solve_mc(hero, villains, community, dead0, need_c, iters, seed)
+        deal_mc(
+            hero, villains, community, dead0, need_c, iters, seed, &mut visit,
+        )
     }
 }
 
-fn solve_exact(
+fn deal_exact<V: FnMut(&[u64], u64, f64)>(
     hero: u64,
     villains: &[Range],
     community: u64,
     dead0: u64,
     need_c: u32,
-) -> Result<EquityResult, String> {
-    let n = villains.len();
-    let mut acc = vec![0.0f64; n + 1];
-    let mut hero_win = 0.0;
-    let mut hero_tie = 0.0;
-    let mut hero_lose = 0.0;
-    let mut iters: u64 = 0;
-    let mut villain_masks = vec![0u64; n];
-
+    visit: &mut V,
+) {
+    let mut holes = vec![0u64; villains.len() + 1];
+    holes[0] = hero;
     enumerate_villains(
         0,
         dead0,
+        1.0,
         villains,
-        &mut villain_masks,
-        &mut |used, masks| {
+        &mut holes,
+        &mut |used, weight, holes| {
             pick(used, need_c, 0, 0, &mut |board_add| {
-                let board = community | board_add;
-                let mut scores: Vec<u32> = Vec::with_capacity(n + 1);
-                scores.push(eval(hero | board));
-                for &vm in masks.iter() {
-                    scores.push(eval(vm | board));
-                }
-                tally_outcome(
-                    &scores,
-                    &mut acc,
-                    &mut hero_win,
-                    &mut hero_tie,
-                    &mut hero_lose,
-                    1.0,
-                );
-                iters += 1;
+                visit(holes, community | board_add, weight);
             });
         },
     );
-
-    finalize(iters, acc, hero_win, hero_tie, hero_lose)
 }
 
-fn enumerate_villains<F: FnMut(u64, &[u64])>(
+/// Walks every non-overlapping villain combo assignment into `holes[1..]`,
+/// passing the product of combo weights along.
+fn enumerate_villains<F: FnMut(u64, f64, &[u64])>(
     i: usize,
     used: u64,
+    weight: f64,
     villains: &[Range],
-    masks: &mut Vec<u64>,
+    holes: &mut Vec<u64>,
     f: &mut F,
 ) {
     if i == villains.len() {
-        f(used, masks);
+        f(used, weight, holes);
         return;
     }
-    let combos: Vec<u64> = villains[i].live_combos(used).map(|c| c.mask).collect();
-    for m in combos {
-        masks[i] = m;
-        enumerate_villains(i + 1, used | m, villains, masks, f);
+    let combos: Vec<(u64, f64)> = villains[i]
+        .live_combos(used)
+        .map(|c| (c.mask, c.weight as f64))
+        .collect();
+    for (m, w) in combos {
+        holes[i + 1] = m;
+        enumerate_villains(i + 1, used | m, weight * w, villains, holes, f);
     }
 }
 
-fn solve_mc(
+#[allow(clippy::too_many_arguments)]
+fn deal_mc<V: FnMut(&[u64], u64, f64)>(
     hero: u64,
     villains: &[Range],
     community: u64,
@@ -299,19 +399,20 @@ fn solve_mc(
     need_c: u32,
     target: u64,
     seed: u64,
-) -> Result<EquityResult, String> {
+    visit: &mut V,
+) -> Result<(), String> {
     let n = villains.len();
-    let live: Vec<Vec<u64>> = villains
+    let live: Vec<Vec<(u64, f64)>> = villains
         .iter()
-        .map(|r| r.live_combos(dead0).map(|c| c.mask).collect())
+        .map(|r| {
+            r.live_combos(dead0)
+                .map(|c| (c.mask, c.weight as f64))
+                .collect()
+        })
         .collect();
 
-    let mut acc = vec![0.0f64; n + 1];
-    let mut hero_win = 0.0;
-    let mut hero_tie = 0.0;
-    let mut hero_lose = 0.0;
     let mut rng = XorShift64::new(seed);
-
+    let mut holes: Vec<u64> = Vec::with_capacity(n + 1);
     let mut iters: u64 = 0;
     let mut attempts: u64 = 0;
     let attempt_cap = target.saturating_mul(50);
@@ -319,24 +420,27 @@ fn solve_mc(
     while iters < target && attempts < attempt_cap {
         attempts += 1;
         let mut used = dead0;
-        let mut villain_masks: Vec<u64> = Vec::with_capacity(n);
+        holes.clear();
+        holes.push(hero);
+        let mut weight = 1.0;
         let mut ok = true;
         for combos in live.iter() {
             let mut tries = 0;
-            let mut found: Option<u64> = None;
+            let mut found: Option<(u64, f64)> = None;
             while tries < 32 {
-                let m = combos[rng.range(combos.len())];
-                if m & used == 0 {
-                    found = Some(m);
+                let c = combos[rng.range(combos.len())];
+                if c.0 & used == 0 {
+                    found = Some(c);
                     break;
                 }
                 tries += 1;
             }
-            let chosen = found.or_else(|| combos.iter().copied().find(|&m| m & used == 0));
+            let chosen = found.or_else(|| combos.iter().copied().find(|c| c.0 & used == 0));
             match chosen {
-                Some(m) => {
-                    villain_masks.push(m);
+                Some((m, w)) => {
+                    holes.push(m);
                     used |= m;
+                    weight *= w;
                 }
                 None => {
                     ok = false;
@@ -348,37 +452,28 @@ fn solve_mc(
             continue;
         }
         let board_add = pick_random_board(used, need_c, &mut rng);
-        let board = community | board_add;
-        let mut scores: Vec<u32> = Vec::with_capacity(n + 1);
-        scores.push(eval(hero | board));
-        for &vm in villain_masks.iter() {
-            scores.push(eval(vm | board));
-        }
-        tally_outcome(
-            &scores,
-            &mut acc,
-            &mut hero_win,
-            &mut hero_tie,
-            &mut hero_lose,
-            1.0,
-        );
+        visit(&holes, community | board_add, weight);
         iters += 1;
     }
 
     if iters == 0 {
         return Err("Monte Carlo could not draw a valid sample".into());
     }
-    finalize(iters, acc, hero_win, hero_tie, hero_lose)
+    Ok(())
 }
 
-fn finalize(
+pub(crate) fn finalize(
     iters: u64,
+    total: f64,
     acc: Vec<f64>,
     hero_win: f64,
     hero_tie: f64,
     hero_lose: f64,
 ) -> Result<EquityResult, String> {
-    let denom = iters as f64;
+    if total <= 0.0 {
+        return Err("villain ranges carry no weight".into());
+    }
+    let denom = total;
     let villain_equity = acc[1..].iter().map(|x| x / denom).collect();
     Ok(EquityResult {
         iterations: iters,
@@ -523,4 +618,15 @@ mod tests {
         assert!(r.hero_win > 0.85);
         assert!(r.hero_lose < 0.1);
     }
+
+    #[test]
+    fn ranges_respect_weights() {
+        let hero = Hand::from_string("AsAd").mask;
+        let community = Hand::from_string("2c3d7h8s9s").mask;
+        let v = Range::parse("KhKc:0.25,AhAc:0.75").unwrap();
+        let r = solve_ranges(hero, &[v], community, 0, 1).unwrap();
+        assert_eq!(r.iterations, 2);
+        assert!((r.hero_win - 0.25).abs() < 1e-9);
+        assert!((r.hero_tie - 0.75).abs() < 1e-9);
+    }
 }
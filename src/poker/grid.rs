@@ -0,0 +1,348 @@
+use crate::poker::range::{offsuit_combos, pair_combos, suited_combos, Combo, RANKS};
+use crate::poker::Range;
+
+pub const GRID_SIZE: usize = 13;
+
+/// One hand class of the 13x13 grid. Keeps a weight per combo (6 for pairs,
+/// 4 for suited, 12 for offsuit) so a grid round-trips partial suits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridCell {
+    pub weights: [f32; 12],
+    pub len: u8,
+}
+
+impl GridCell {
+    fn empty(len: u8) -> Self {
+        Self {
+            weights: [0.0; 12],
+            len,
+        }
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights[..self.len as usize]
+    }
+
+    /// Average weight over every combo of the class.
+    pub fn frequency(&self) -> f32 {
+        self.weights().iter().sum::<f32>() / self.len as f32
+    }
+
+    /// Number of combos with a non-zero weight.
+    pub fn present(&self) -> usize {
+        self.weights().iter().filter(|&&w| w > 0.0).count()
+    }
+
+    /// True when combos of the class carry different weights, e.g. only the
+    /// spade combo of AKs is in range.
+    pub fn is_partial(&self) -> bool {
+        let w = self.weights();
+        w.iter().any(|&x| x != w[0])
+    }
+
+    pub fn set_frequency(&mut self, f: f32) {
+        for w in self.weights[..self.len as usize].iter_mut() {
+            *w = f;
+        }
+    }
+}
+
+/// Range as a 13x13 matrix: row/column 0 is the ace, pairs on the diagonal,
+/// suited hands above it and offsuit hands below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeGrid {
+    pub cells: [[GridCell; GRID_SIZE]; GRID_SIZE],
+}
+
+impl Default for RangeGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn rank_of(i: usize) -> u8 {
+    (GRID_SIZE - 1 - i) as u8
+}
+
+fn class_len(row: usize, col: usize) -> u8 {
+    if row == col {
+        6
+    } else if row < col {
+        4
+    } else {
+        12
+    }
+}
+
+/// Combos of the class at (row, col), in the fixed order used for `weights`.
+pub fn class_combos(row: usize, col: usize) -> Vec<Combo> {
+    let (r1, r2) = (rank_of(row), rank_of(col));
+    if row == col {
+        pair_combos(r1)
+    } else if row < col {
+        suited_combos(r1, r2)
+    } else {
+        offsuit_combos(r2, r1)
+    }
+}
+
+/// Class label at (row, col), e.g. "AA", "AKs", "AKo".
+pub fn class_label(row: usize, col: usize) -> String {
+    let a = RANKS[rank_of(row.min(col)) as usize] as char;
+    let b = RANKS[rank_of(row.max(col)) as usize] as char;
+    if row == col {
+        format!("{}{}", a, b)
+    } else if row < col {
+        format!("{}{}s", a, b)
+    } else {
+        format!("{}{}o", a, b)
+    }
+}
+
+/// Grid position and combo slot of a 2-card mask.
+pub fn locate(mask: u64) -> Option<(usize, usize, usize)> {
+    if mask.count_ones() != 2 {
+        return None;
+    }
+    let lo = mask.trailing_zeros();
+    let hi = 63 - mask.leading_zeros();
+    let (rh, sh) = (hi / 4, hi % 4);
+    let (rl, sl) = (lo / 4, lo % 4);
+    let (row, col) = if rh == rl {
+        (12 - rh, 12 - rh)
+    } else if sh == sl {
+        (12 - rh, 12 - rl)
+    } else {
+        (12 - rl, 12 - rh)
+    };
+    let (row, col) = (row as usize, col as usize);
+    let slot = class_combos(row, col).iter().position(|c| c.mask == mask)?;
+    Some((row, col, slot))
+}
+
+impl RangeGrid {
+    pub fn new() -> Self {
+        let mut cells = [[GridCell::empty(0); GRID_SIZE]; GRID_SIZE];
+        for (row, line) in cells.iter_mut().enumerate() {
+            for (col, cell) in line.iter_mut().enumerate() {
+                *cell = GridCell::empty(class_len(row, col));
+            }
+        }
+        Self { cells }
+    }
+
+    pub fn from_frequencies(freq: &[[f32; GRID_SIZE]; GRID_SIZE]) -> Self {
+        let mut grid = Self::new();
+        for (row, line) in freq.iter().enumerate() {
+            for (col, &f) in line.iter().enumerate() {
+                grid.cells[row][col].set_frequency(f);
+            }
+        }
+        grid
+    }
+
+    pub fn frequencies(&self) -> [[f32; GRID_SIZE]; GRID_SIZE] {
+        let mut out = [[0.0; GRID_SIZE]; GRID_SIZE];
+        for (row, line) in self.cells.iter().enumerate() {
+            for (col, cell) in line.iter().enumerate() {
+                out[row][col] = cell.frequency();
+            }
+        }
+        out
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> &GridCell {
+        &self.cells[row][col]
+    }
+
+    pub fn frequency(&self, row: usize, col: usize) -> f32 {
+        self.cells[row][col].frequency()
+    }
+
+    pub fn set_frequency(&mut self, row: usize, col: usize, f: f32) {
+        self.cells[row][col].set_frequency(f);
+    }
+
+    /// Weighted combo count, out of 1326.
+    pub fn combo_count(&self) -> f32 {
+        self.cells
+            .iter()
+            .flatten()
+            .map(|c| c.weights().iter().sum::<f32>())
+            .sum()
+    }
+
+    /// ASCII matrix with percentages per class. Classes holding only some
+    /// suit combos are marked with `*`, empty classes with `.`.
+    pub fn render(&self) -> String {
+        let mut out = String::from("   ");
+        for i in 0..GRID_SIZE {
+            out.push_str(&format!("{:>4} ", RANKS[rank_of(i) as usize] as char));
+        }
+        out.push('\n');
+        for (row, line) in self.cells.iter().enumerate() {
+            out.push_str(&format!("{:>3}", RANKS[rank_of(row) as usize] as char));
+            for cell in line.iter() {
+                let f = cell.frequency();
+                let text = if f <= 0.0 {
+                    ".".to_string()
+                } else {
+                    format!("{:.0}", f * 100.0)
+                };
+                let mark = if cell.is_partial() { '*' } else { ' ' };
+                out.push_str(&format!("{:>4}{}", text, mark));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// CSV with a header row and column of ranks, frequencies in [0, 1].
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for i in 0..GRID_SIZE {
+            out.push(',');
+            out.push(RANKS[rank_of(i) as usize] as char);
+        }
+        out.push('\n');
+        for (row, line) in self.cells.iter().enumerate() {
+            out.push(RANKS[rank_of(row) as usize] as char);
+            for cell in line.iter() {
+                out.push_str(&format!(",{}", cell.frequency()));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Parse the `to_csv` layout. The header row and column are optional.
+    pub fn from_csv(s: &str) -> Result<Self, String> {
+        let mut freq = [[0.0f32; GRID_SIZE]; GRID_SIZE];
+        let mut row = 0;
+        for line in s.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            if fields.len() == GRID_SIZE + 1 {
+                if fields[0].is_empty() {
+                    continue;
+                }
+                fields.remove(0);
+            }
+            if fields.len() != GRID_SIZE {
+                return Err(format!("csv row {} has {} columns", row, fields.len()));
+            }
+            if row >= GRID_SIZE {
+                return Err("csv has more than 13 rows".into());
+            }
+            for (col, f) in fields.iter().enumerate() {
+                let v: f32 = f
+                    .parse()
+                    .map_err(|_| format!("bad frequency at row {}: {}", row, f))?;
+                if !(0.0..=1.0).contains(&v) {
+                    return Err(format!("frequency out of [0, 1]: {}", v));
+                }
+                freq[row][col] = v;
+            }
+            row += 1;
+        }
+        if row != GRID_SIZE {
+            return Err(format!("csv has {} rows, expected 13", row));
+        }
+        Ok(Self::from_frequencies(&freq))
+    }
+}
+
+impl Range {
+    pub fn to_grid(&self) -> RangeGrid {
+        let mut grid = RangeGrid::new();
+        for c in self.combos.iter() {
+            if let Some((row, col, slot)) = locate(c.mask) {
+                grid.cells[row][col].weights[slot] = c.weight;
+            }
+        }
+        grid
+    }
+
+    pub fn from_grid(grid: &RangeGrid) -> Self {
+        let mut combos = Vec::new();
+        for (row, line) in grid.cells.iter().enumerate() {
+            for (col, cell) in line.iter().enumerate() {
+                for (c, &w) in class_combos(row, col).iter().zip(cell.weights()) {
+                    if w > 0.0 {
+                        combos.push(Combo::with_weight(c.mask, w));
+                    }
+                }
+            }
+        }
+        combos.sort_by_key(|c| c.mask);
+        Self { combos }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    #[test]
+    fn labels() {
+        assert_eq!(class_label(0, 0), "AA");
+        assert_eq!(class_label(0, 1), "AKs");
+        assert_eq!(class_label(1, 0), "AKo");
+        assert_eq!(class_label(12, 12), "22");
+    }
+
+    #[test]
+    fn full_range_counts() {
+        let grid = Range::any().to_grid();
+        assert!((grid.combo_count() - 1326.0).abs() < 1e-3);
+        assert!(grid.frequencies().iter().flatten().all(|&f| f == 1.0));
+    }
+
+    #[test]
+    fn class_placement() {
+        let grid = Range::from_notation("AKs,QQ,T9o").unwrap().to_grid();
+        assert_eq!(grid.frequency(0, 1), 1.0);
+        assert_eq!(grid.frequency(1, 0), 0.0);
+        assert_eq!(grid.frequency(2, 2), 1.0);
+        assert_eq!(grid.frequency(5, 4), 1.0);
+    }
+
+    #[test]
+    fn partial_suits_round_trip() {
+        let r = Range::from_notation("AsKs,AhKh,QQ").unwrap();
+        let grid = r.to_grid();
+        assert_eq!(grid.cell(0, 1).present(), 2);
+        assert!(grid.cell(0, 1).is_partial());
+        assert!(!grid.cell(2, 2).is_partial());
+        let back = Range::from_grid(&grid);
+        assert_eq!(back.combos, r.combos);
+        let ks = Hand::from_string("AsKs").mask;
+        assert!(back.combos.iter().any(|c| c.mask == ks));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let mut grid = Range::from_notation("JJ+,AQs+").unwrap().to_grid();
+        grid.set_frequency(0, 3, 0.5);
+        let csv = grid.to_csv();
+        let back = RangeGrid::from_csv(&csv).unwrap();
+        assert_eq!(back.frequencies(), grid.frequencies());
+        assert_eq!(Range::from_grid(&back).len(), 4 * 6 + 3 * 4);
+    }
+
+    #[test]
+    fn csv_rejects_bad_shape() {
+        assert!(RangeGrid::from_csv("1,0\n0,1").is_err());
+    }
+
+    #[test]
+    fn render_marks() {
+        let text = Range::from_notation("AsKs").unwrap().to_grid().render();
+        assert_eq!(text.lines().count(), 14);
+        assert!(text.contains("25*"));
+    }
+}
@@ -1,6 +1,11 @@
+use crate::poker::card::Card;
+use crate::poker::rules::FULL_DECK;
+use std::fmt;
+use std::str::FromStr;
+
 pub const SUIT_COUNT: u32 = 4;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Hand {
     pub mask: u64,
 }
@@ -10,6 +15,10 @@ impl Hand {
         self.mask.count_ones()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
     pub fn from_mask(x: u64) -> Self {
         Self { mask: x }
     }
@@ -32,6 +41,103 @@ impl Hand {
     pub fn overlap(&self, other: &Self) -> bool {
         self.mask & other.mask != 0
     }
+
+    /// Cards from the highest index down, i.e. aces first, `h` before `s`
+    /// within a rank. Bits past the 52 cards are ignored.
+    pub fn cards(&self) -> impl Iterator<Item = Card> {
+        let mut m = self.mask & FULL_DECK;
+        std::iter::from_fn(move || {
+            if m == 0 {
+                return None;
+            }
+            let i = 63 - m.leading_zeros();
+            m &= !(1u64 << i);
+            Card::from_index(i as u8)
+        })
+    }
+
+    pub fn contains(&self, card: Card) -> bool {
+        self.mask & card.mask() != 0
+    }
+
+    pub fn contains_all(&self, other: &Self) -> bool {
+        self.mask & other.mask == other.mask
+    }
+
+    pub fn insert(&mut self, card: Card) {
+        self.mask |= card.mask();
+    }
+
+    pub fn remove(&mut self, card: Card) {
+        self.mask &= !card.mask();
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_mask(self.mask | other.mask)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::from_mask(self.mask & other.mask)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        Self::from_mask(self.mask & !other.mask)
+    }
+
+    /// Ranks present in any suit, bit `r` for rank `r`.
+    pub fn ranks(&self) -> u16 {
+        self.cards().fold(0, |acc, c| acc | (1 << c.rank()))
+    }
+
+    /// Ranks present in `suit`, bit `r` for rank `r`.
+    pub fn suit_ranks(&self, suit: u8) -> u16 {
+        self.cards()
+            .filter(|c| c.suit() == suit)
+            .fold(0, |acc, c| acc | (1 << c.rank()))
+    }
+}
+
+impl From<Card> for Hand {
+    fn from(card: Card) -> Self {
+        Self::from_mask(card.mask())
+    }
+}
+
+impl FromIterator<Card> for Hand {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+        Self::from_mask(iter.into_iter().fold(0, |acc, c| acc | c.mask()))
+    }
+}
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.cards() {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Strict counterpart of `from_string`: rejects unknown characters and
+/// repeated cards. Spaces and commas between cards are allowed.
+impl FromStr for Hand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let compact: String = s.chars().filter(|c| !matches!(c, ' ' | ',')).collect();
+        if compact.len() % 2 == 1 || !compact.is_ascii() {
+            return Err(format!("bad hand: {}", s));
+        }
+        let mut hand = Self::default();
+        for i in (0..compact.len()).step_by(2) {
+            let card: Card = compact[i..i + 2].parse()?;
+            if hand.contains(card) {
+                return Err(format!("duplicate card: {}", card));
+            }
+            hand.insert(card);
+        }
+        Ok(hand)
+    }
 }
 
 #[cfg(test)]
@@ -56,6 +162,49 @@ mod tests {
         let expected: u64 = [48, 49, 50, 51, 43].iter().map(|x| 1u64 << x).sum();
         assert_eq!(output.mask, expected);
     }
+    #[test]
+    fn cards_in_stable_order() {
+        let h = Hand::from_string("2cAsKdAh");
+        let cards: Vec<String> = h.cards().map(|c| c.to_string()).collect();
+        assert_eq!(cards, ["Ah", "As", "Kd", "2c"]);
+        assert_eq!(h.to_string(), "AhAsKd2c");
+        let stray = Hand::from_mask(h.mask | 1u64 << 60);
+        assert_eq!(stray.cards().count(), 4);
+    }
+
+    #[test]
+    fn from_str_round_trip() {
+        let h: Hand = "Ah Kd, 7c".parse().unwrap();
+        assert_eq!(h.len(), 3);
+        assert_eq!(h.to_string().parse::<Hand>().unwrap(), h);
+        assert!("AhAh".parse::<Hand>().is_err());
+        assert!("AhK".parse::<Hand>().is_err());
+        assert!("Xh".parse::<Hand>().is_err());
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = Hand::from_string("AsKsQs");
+        let b = Hand::from_string("QsJs");
+        assert_eq!(a.union(&b), Hand::from_string("AsKsQsJs"));
+        assert_eq!(a.intersection(&b), Hand::from_string("Qs"));
+        assert_eq!(a.difference(&b), Hand::from_string("AsKs"));
+        assert!(a.contains("Ks".parse().unwrap()));
+        assert!(!a.contains("Kh".parse().unwrap()));
+        assert!(a.contains_all(&Hand::from_string("AsQs")));
+        let mut c: Hand = a.cards().filter(|c| c.rank() > 10).collect();
+        c.remove("As".parse().unwrap());
+        assert_eq!(c, Hand::from_string("Ks"));
+    }
+
+    #[test]
+    fn rank_and_suit_masks() {
+        let h = Hand::from_string("AsAhKs2d");
+        assert_eq!(h.ranks(), (1 << 12) | (1 << 11) | 1);
+        assert_eq!(h.suit_ranks(0), (1 << 12) | (1 << 11));
+        assert_eq!(h.suit_ranks(2), 1);
+    }
+
     #[test]
     fn parse_empty() {
         let output = Hand::from_string("");
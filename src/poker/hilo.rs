@@ -0,0 +1,332 @@
+use crate::poker::evaluator::eval;
+use crate::poker::game::deal_out;
+use crate::poker::omaha::{eval_omaha, for_each_subset};
+use crate::poker::rules::{split_best, GameRules};
+use crate::poker::Range;
+use std::ops::RangeInclusive;
+
+/// Ranks A through 8 as a low mask: bit 0 is the ace, bit 7 the eight.
+fn low_ranks(mask: u64) -> u8 {
+    let mut out = 0u8;
+    for r in 0..13u32 {
+        if (mask >> (r * 4)) & 0xF == 0 {
+            continue;
+        }
+        match r {
+            12 => out |= 1,
+            0..=6 => out |= 1 << (r + 1),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Best eight-or-better low among any five cards of `mask`: five distinct
+/// ranks from ace to eight, aces low, straights and flushes ignored. `None`
+/// when no low qualifies; otherwise a higher u32 is the better low.
+pub fn eval_low8(mask: u64) -> Option<u32> {
+    let mut ranks = low_ranks(mask);
+    if ranks.count_ones() < 5 {
+        return None;
+    }
+    while ranks.count_ones() > 5 {
+        ranks &= !(1 << (7 - ranks.leading_zeros()));
+    }
+    Some(0x100 - ranks as u32)
+}
+
+/// Eight-or-better low using exactly two cards from `hole` and three from
+/// `board`.
+pub fn eval_omaha_low8(hole: u64, board: u64) -> Option<u32> {
+    let mut best = None;
+    for_each_subset(hole, 2, &mut |h| {
+        for_each_subset(board, 3, &mut |b| {
+            best = best.max(eval_low8(h | b));
+        });
+    });
+    best
+}
+
+/// Which cards make a hand in a split-pot game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HiLoGame {
+    /// Any five of hole and board, two hole cards.
+    Holdem,
+    /// Exactly two from 4 or 5 hole cards, three from the board.
+    Omaha,
+}
+
+/// Player `i`'s fraction of a split pot: half to the best high and half to
+/// the best qualifying low, ties sharing their half, the high taking
+/// everything when no low qualifies.
+fn hilo_share(high: &[u32], low: &[Option<u32>], i: usize) -> f64 {
+    let best_high = *high.iter().max().unwrap();
+    let best_low = *low.iter().max().unwrap();
+    let high_pot = if best_low.is_some() { 0.5 } else { 1.0 };
+    let mut share = 0.0;
+    if high[i] == best_high {
+        share += high_pot / high.iter().filter(|&&s| s == best_high).count() as f64;
+    }
+    if best_low.is_some() && low[i] == best_low {
+        share += 0.5 / low.iter().filter(|&&s| s == best_low).count() as f64;
+    }
+    share
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct HiLoResult {
+    pub iterations: u64,
+    /// Share of the pot per player, hero first.
+    pub equity: Vec<f64>,
+    /// Chance to win or tie the high half.
+    pub high: Vec<f64>,
+    /// Chance to win or tie a qualifying low half.
+    pub low: Vec<f64>,
+    /// Chance to take the whole pot alone.
+    pub scoop: Vec<f64>,
+    /// Chance any player makes a qualifying low.
+    pub low_qualified: f64,
+}
+
+impl HiLoResult {
+    pub(crate) fn new(players: usize) -> Self {
+        Self {
+            equity: vec![0.0; players],
+            high: vec![0.0; players],
+            low: vec![0.0; players],
+            scoop: vec![0.0; players],
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn tally(&mut self, high: &[u32], low: &[Option<u32>], weight: f64) {
+        let best_high = *high.iter().max().unwrap();
+        let best_low = *low.iter().max().unwrap();
+        if best_low.is_some() {
+            self.low_qualified += weight;
+        }
+        for i in 0..high.len() {
+            let share = hilo_share(high, low, i);
+            if high[i] == best_high {
+                self.high[i] += weight;
+            }
+            if best_low.is_some() && low[i] == best_low {
+                self.low[i] += weight;
+            }
+            self.equity[i] += share * weight;
+            if share >= 1.0 - 1e-12 {
+                self.scoop[i] += weight;
+            }
+        }
+    }
+
+    pub(crate) fn finalize(mut self, iterations: u64, total: f64) -> Result<Self, String> {
+        if total <= 0.0 {
+            return Err("villain ranges carry no weight".into());
+        }
+        self.iterations = iterations;
+        for v in [
+            &mut self.equity,
+            &mut self.high,
+            &mut self.low,
+            &mut self.scoop,
+        ] {
+            v.iter_mut().for_each(|x| *x /= total);
+        }
+        self.low_qualified /= total;
+        Ok(self)
+    }
+}
+
+/// Split-pot rules with an eight-or-better low, for `solve_equity`.
+#[derive(Debug, Clone, Copy)]
+pub struct HiLo(pub HiLoGame);
+
+impl HiLo {
+    fn high(&self, hole: u64, board: u64) -> u32 {
+        match self.0 {
+            HiLoGame::Holdem => eval(hole | board),
+            HiLoGame::Omaha => eval_omaha(hole, board),
+        }
+    }
+
+    fn low(&self, hole: u64, board: u64) -> Option<u32> {
+        match self.0 {
+            HiLoGame::Holdem => eval_low8(hole | board),
+            HiLoGame::Omaha => eval_omaha_low8(hole, board),
+        }
+    }
+}
+
+impl GameRules for HiLo {
+    fn hole_cards(&self) -> RangeInclusive<u32> {
+        match self.0 {
+            HiLoGame::Holdem => 2..=2,
+            HiLoGame::Omaha => 4..=5,
+        }
+    }
+
+    fn score(&self, hole: u64, board: u64) -> u32 {
+        self.high(hole, board)
+    }
+
+    fn cost(&self, hole_cards: u32) -> u128 {
+        match self.0 {
+            HiLoGame::Holdem => 2,
+            HiLoGame::Omaha => 10 * hole_cards as u128 * (hole_cards as u128 - 1),
+        }
+    }
+
+    fn split(&self, holes: &[u64], board: u64, shares: &mut [f64]) {
+        let low: Vec<Option<u32>> = holes.iter().map(|&h| self.low(h, board)).collect();
+        if low.iter().all(|l| l.is_none()) {
+            for (share, &h) in shares.iter_mut().zip(holes) {
+                *share = self.high(h, board) as f64;
+            }
+            split_best(shares);
+            return;
+        }
+        let high: Vec<u32> = holes.iter().map(|&h| self.high(h, board)).collect();
+        for (i, share) in shares.iter_mut().enumerate() {
+            *share = hilo_share(&high, &low, i);
+        }
+    }
+}
+
+/// Hi-lo split pot equity with an eight-or-better low, with per player
+/// high, low and scoop chances. `community` is empty preflop or holds 3-5
+/// cards.
+pub fn solve_hilo(
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+    game: HiLoGame,
+) -> Result<HiLoResult, String> {
+    let rules = HiLo(game);
+    let mut out = HiLoResult::new(villains.len() + 1);
+    let mut iterations = 0;
+    let mut total = 0.0;
+    let mut high = Vec::with_capacity(villains.len() + 1);
+    let mut low = Vec::with_capacity(villains.len() + 1);
+    deal_out(
+        &rules,
+        hero,
+        villains,
+        community,
+        max_iterations,
+        seed,
+        |holes, board, weight| {
+            high.clear();
+            low.clear();
+            for &h in holes {
+                high.push(rules.high(h, board));
+                low.push(rules.low(h, board));
+            }
+            out.tally(&high, &low, weight);
+            iterations += 1;
+            total += weight;
+        },
+    )?;
+    out.finalize(iterations, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    #[test]
+    fn low_qualifies() {
+        assert!(eval_low8(mask("As2c3d4h5s")).is_some());
+        assert!(eval_low8(mask("As2c3d4h9s")).is_none());
+        assert!(eval_low8(mask("As2c3d4hAh9s")).is_none());
+        assert!(eval_low8(mask("Ks2c3d4h8s7h6d")).is_some());
+    }
+
+    #[test]
+    fn low_ordering() {
+        let wheel = eval_low8(mask("As2c3d4h5s")).unwrap();
+        let six = eval_low8(mask("As2c3d4h6s")).unwrap();
+        let seven_six = eval_low8(mask("7s6c3d2hAs")).unwrap();
+        let seven_five = eval_low8(mask("7s5c4d3h2s")).unwrap();
+        assert!(wheel > six);
+        assert!(six > seven_five);
+        assert!(seven_five > seven_six);
+        assert_eq!(
+            eval_low8(mask("As2c3d4h5sKdQh")),
+            eval_low8(mask("As2c3d4h5d"))
+        );
+    }
+
+    #[test]
+    fn omaha_low_needs_two_hole_cards() {
+        let board = mask("2c3d4hKsQd");
+        assert!(eval_omaha_low8(mask("AsKhQcJc"), board).is_none());
+        assert!(eval_omaha_low8(mask("As5hQcJc"), board).is_some());
+        assert!(eval_omaha_low8(mask("AsKh"), mask("2c3d4h5s6d")).is_none());
+    }
+
+    #[test]
+    fn scoop_without_low() {
+        let hero = mask("AsAhKsKh");
+        let v = Range::from_omaha_notation("QsQhJsJh", 4).unwrap();
+        let board = mask("AdKdTc9c9d");
+        let r = solve_hilo(hero, &[v], board, 0, 1, HiLoGame::Omaha).unwrap();
+        assert_eq!(r.low_qualified, 0.0);
+        assert_eq!(r.equity, vec![1.0, 0.0]);
+        assert_eq!(r.scoop, vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn split_high_and_low() {
+        let hero = mask("AsAhKsKh");
+        let v = Range::from_omaha_notation("2s3h9c9d", 4).unwrap();
+        let board = mask("4c5d8hKdQs");
+        let r = solve_hilo(hero, &[v], board, 0, 1, HiLoGame::Omaha).unwrap();
+        assert_eq!(r.low_qualified, 1.0);
+        assert_eq!(r.equity, vec![0.5, 0.5]);
+        assert_eq!(r.high, vec![1.0, 0.0]);
+        assert_eq!(r.low, vec![0.0, 1.0]);
+        assert_eq!(r.scoop, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn quartered_low() {
+        let hero = mask("KhJs2s3h");
+        let v = Range::from_omaha_notation("2c3cQdQh", 4).unwrap();
+        let board = mask("4c5d8hKdTs");
+        let r = solve_hilo(hero, &[v], board, 0, 1, HiLoGame::Omaha).unwrap();
+        assert_eq!(r.equity, vec![0.75, 0.25]);
+        assert_eq!(r.low, vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn rules_match_solve_hilo() {
+        use crate::poker::solve_equity;
+        let hero = mask("KhJs2s3h");
+        let v = [Range::from_omaha_notation("2c3cQdQh", 4).unwrap()];
+        let board = mask("4c5d8hKd");
+        let game = HiLoGame::Omaha;
+        let detail = solve_hilo(hero, &v, board, 0, 1, game).unwrap();
+        let r = solve_equity(&HiLo(game), hero, &v, board, 0, 1).unwrap();
+        assert!((r.villain_equity[0] - detail.equity[1]).abs() < 1e-9);
+        assert!((r.hero_win - detail.scoop[0]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn holdem_hilo_sums_to_one() {
+        let hero = mask("As2s");
+        let board = mask("3c4d9h");
+        let v = Range::from_notation("KK").unwrap();
+        let r = solve_hilo(hero, &[v], board, 0, 1, HiLoGame::Holdem).unwrap();
+        assert!((r.equity.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(r.low[0] > 0.5);
+        assert_eq!(r.low[1], 0.0);
+    }
+}
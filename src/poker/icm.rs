@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+
+use crate::poker::deck::XorShift64;
+use crate::poker::game::{binom, EquityResult, DEFAULT_MC_ITERATIONS, EXACT_WORK_LIMIT};
+
+fn validate(stacks: &[f64], payouts: &[f64]) -> Result<(), String> {
+    if stacks.is_empty() || stacks.len() > 64 {
+        return Err("need 1 to 64 players".into());
+    }
+    if stacks.iter().any(|s| s.is_nan() || *s < 0.0) || stacks.iter().all(|&s| s == 0.0) {
+        return Err("stacks must be non-negative with some chips in play".into());
+    }
+    if payouts.iter().any(|p| p.is_nan() || *p < 0.0) {
+        return Err("payouts must be non-negative".into());
+    }
+    Ok(())
+}
+
+/// Tournament equity of each stack by Malmuth-Harville: a player finishes
+/// in the next open place with probability proportional to their chips
+/// among those left. Busted players share the lowest places evenly.
+/// Exact over subsets of placed players, so cheap only for few paid places.
+pub fn icm_exact(stacks: &[f64], payouts: &[f64]) -> Result<Vec<f64>, String> {
+    validate(stacks, payouts)?;
+    let n = stacks.len();
+    let mut out = vec![0.0; n];
+    let mut level: BTreeMap<u64, f64> = BTreeMap::from([(0, 1.0)]);
+    for &payout in payouts.iter().take(n) {
+        let mut next: BTreeMap<u64, f64> = BTreeMap::new();
+        for (&placed, &p) in &level {
+            let left: Vec<usize> = (0..n).filter(|&i| placed & 1u64 << i == 0).collect();
+            let total: f64 = left.iter().map(|&i| stacks[i]).sum();
+            for &i in &left {
+                let q = if total > 0.0 {
+                    stacks[i] / total
+                } else {
+                    1.0 / left.len() as f64
+                };
+                if q > 0.0 {
+                    out[i] += p * q * payout;
+                    *next.entry(placed | 1u64 << i).or_insert(0.0) += p * q;
+                }
+            }
+        }
+        level = next;
+    }
+    Ok(out)
+}
+
+/// Malmuth-Harville equities estimated from `samples` finishing orders.
+/// Sorting players by `ln(u) / stack` for uniform `u` draws an order with
+/// exactly the Harville probabilities, so this scales to large fields.
+pub fn icm_monte_carlo(
+    stacks: &[f64],
+    payouts: &[f64],
+    samples: u64,
+    seed: u64,
+) -> Result<Vec<f64>, String> {
+    validate(stacks, payouts)?;
+    if samples == 0 {
+        return Err("need at least one sample".into());
+    }
+    let n = stacks.len();
+    let mut rng = XorShift64::new(seed);
+    let mut out = vec![0.0; n];
+    let mut keys: Vec<(f64, f64, usize)> = Vec::with_capacity(n);
+    for _ in 0..samples {
+        keys.clear();
+        for (i, &s) in stacks.iter().enumerate() {
+            // 1 - u avoids ln(0); busted players sort last in random order
+            let u = 1.0 - rng.next_f64();
+            let key = if s > 0.0 {
+                u.ln() / s
+            } else {
+                f64::NEG_INFINITY
+            };
+            keys.push((key, rng.next_f64(), i));
+        }
+        keys.sort_unstable_by(|a, b| b.0.total_cmp(&a.0).then(b.1.total_cmp(&a.1)));
+        for (&(_, _, i), &payout) in keys.iter().zip(payouts) {
+            out[i] += payout;
+        }
+    }
+    for x in out.iter_mut() {
+        *x /= samples as f64;
+    }
+    Ok(out)
+}
+
+/// Malmuth-Harville equities, exact when the subsets to visit are few and
+/// by Monte Carlo otherwise.
+pub fn icm(stacks: &[f64], payouts: &[f64]) -> Result<Vec<f64>, String> {
+    let n = stacks.len() as u32;
+    let places = (payouts.len() as u32).min(n);
+    let work: u128 = (0..places).map(|k| binom(n, k) * (n - k) as u128).sum();
+    if work <= EXACT_WORK_LIMIT {
+        icm_exact(stacks, payouts)
+    } else {
+        icm_monte_carlo(stacks, payouts, DEFAULT_MC_ITERATIONS, 1)
+    }
+}
+
+/// Hero's value of folding or calling an all-in, in chips and in prize
+/// money.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AllInEv {
+    pub fold_chips: f64,
+    pub call_chips: f64,
+    pub fold: f64,
+    pub call: f64,
+}
+
+impl AllInEv {
+    pub fn should_call(&self) -> bool {
+        self.call > self.fold
+    }
+}
+
+/// `villain` is all-in and `hero` decides, holding `equity` against the
+/// shove. `stacks` are chips before the hand, `posted` what each player has
+/// put in so far (the villain's whole stack among them); everyone else has
+/// folded and their chips stay in the pot.
+pub fn all_in_ev(
+    stacks: &[f64],
+    payouts: &[f64],
+    posted: &[f64],
+    hero: usize,
+    villain: usize,
+    equity: &EquityResult,
+) -> Result<AllInEv, String> {
+    if posted.len() != stacks.len()
+        || hero >= stacks.len()
+        || villain >= stacks.len()
+        || hero == villain
+    {
+        return Err("need a post per player and two distinct players".into());
+    }
+    if posted
+        .iter()
+        .zip(stacks)
+        .any(|(&p, &s)| !(p >= 0.0 && p <= s))
+    {
+        return Err("posts must be between 0 and the stack".into());
+    }
+    if posted[villain] != stacks[villain] {
+        return Err("the villain must have posted their whole stack".into());
+    }
+    let after: Vec<f64> = stacks.iter().zip(posted).map(|(s, p)| s - p).collect();
+    let dead: f64 = posted
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != hero && i != villain)
+        .map(|(_, p)| p)
+        .sum();
+
+    let mut fold = after.clone();
+    fold[villain] = stacks[villain] + posted.iter().sum::<f64>() - posted[villain];
+
+    // the shorter stack decides how much is contested
+    let covered = stacks[hero].min(stacks[villain]);
+    let outcome = |hero_gets: f64, villain_gets: f64| {
+        let mut s = after.clone();
+        s[hero] = stacks[hero] - covered + hero_gets;
+        s[villain] = stacks[villain] - covered + villain_gets;
+        s
+    };
+    let win = outcome(2.0 * covered + dead, 0.0);
+    let tie = outcome(covered + dead / 2.0, covered + dead / 2.0);
+    let lose = outcome(0.0, 2.0 * covered + dead);
+
+    let odds = [equity.hero_win, equity.hero_tie, equity.hero_lose];
+    let spread = |values: [f64; 3]| odds.iter().zip(values).map(|(p, v)| p * v).sum::<f64>();
+    let prize = |s: &[f64]| icm(s, payouts).map(|e| e[hero]);
+    Ok(AllInEv {
+        fold_chips: fold[hero],
+        call_chips: spread([win[hero], tie[hero], lose[hero]]),
+        fold: prize(&fold)?,
+        call: spread([prize(&win)?, prize(&tie)?, prize(&lose)?]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, eps: f64) -> bool {
+        (a - b).abs() < eps
+    }
+
+    #[test]
+    fn heads_up_is_chip_proportional() {
+        let e = icm_exact(&[3000.0, 1000.0], &[70.0, 30.0]).unwrap();
+        assert!(close(e[0], 0.75 * 70.0 + 0.25 * 30.0, 1e-9));
+        assert!(close(e[1], 40.0, 1e-9));
+    }
+
+    #[test]
+    fn three_handed_harville() {
+        let e = icm_exact(&[50.0, 30.0, 20.0], &[0.5, 0.3, 0.2]).unwrap();
+        // first 0.5, second 0.3 * 5/7 + 0.2 * 5/8, third the rest
+        let second = 0.3 * 5.0 / 7.0 + 0.2 * 5.0 / 8.0;
+        let expected = 0.5 * 0.5 + second * 0.3 + (1.0 - 0.5 - second) * 0.2;
+        assert!(close(e[0], expected, 1e-12));
+        assert!(close(e.iter().sum::<f64>(), 1.0, 1e-12));
+        // a busted player takes the last paid place
+        let busted = icm_exact(&[60.0, 40.0, 0.0], &[0.5, 0.3, 0.2]).unwrap();
+        assert!(close(busted[2], 0.2, 1e-12));
+    }
+
+    #[test]
+    fn monte_carlo_matches_exact() {
+        let stacks = [4000.0, 2500.0, 1800.0, 1200.0, 500.0];
+        let payouts = [50.0, 30.0, 20.0];
+        let exact = icm_exact(&stacks, &payouts).unwrap();
+        let sampled = icm_monte_carlo(&stacks, &payouts, 200_000, 7).unwrap();
+        for (a, b) in exact.iter().zip(&sampled) {
+            assert!(close(*a, *b, 0.3), "{} vs {}", a, b);
+        }
+        assert_eq!(icm(&stacks, &payouts).unwrap(), exact);
+    }
+
+    #[test]
+    fn bubble_folds_a_chip_ev_call() {
+        // three left, two paid: calling a coin flip for it all gains chips
+        // but the fold locks up a min cash more often
+        let stacks = [4000.0, 4000.0, 2000.0];
+        let payouts = [50.0, 50.0];
+        let equity = EquityResult {
+            iterations: 1,
+            hero_win: 0.55,
+            hero_tie: 0.0,
+            hero_lose: 0.45,
+            villain_equity: vec![0.45],
+        };
+        let ev = all_in_ev(&stacks, &payouts, &[0.0, 4000.0, 0.0], 0, 1, &equity).unwrap();
+        assert!(ev.call_chips > ev.fold_chips);
+        assert!(close(ev.call_chips, 0.55 * 8000.0, 1e-9));
+        assert!(!ev.should_call(), "call {} fold {}", ev.call, ev.fold);
+        // heads-up winner take all, $EV follows chips
+        let ev = all_in_ev(&[3000.0, 1000.0], &[100.0], &[100.0, 1000.0], 0, 1, &equity).unwrap();
+        assert!(close(ev.fold_chips, 2900.0, 1e-9));
+        assert!(close(ev.call, ev.call_chips / 40.0, 1e-9));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(icm(&[], &[1.0]).is_err());
+        assert!(icm(&[100.0, -5.0], &[1.0]).is_err());
+        assert!(icm(&[0.0, 0.0], &[1.0]).is_err());
+        let equity = EquityResult::default();
+        assert!(all_in_ev(&[10.0, 10.0], &[1.0], &[0.0, 10.0], 0, 0, &equity).is_err());
+        assert!(all_in_ev(&[10.0, 10.0], &[1.0], &[0.0, 20.0], 0, 1, &equity).is_err());
+        assert!(all_in_ev(&[10.0, 10.0], &[1.0], &[0.0, 5.0], 0, 1, &equity).is_err());
+    }
+}
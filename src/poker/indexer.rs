@@ -0,0 +1,502 @@
+use crate::poker::evaluator::pack_suit;
+use crate::poker::game::binom;
+use crate::poker::hand::SUIT_COUNT;
+use crate::poker::isomorphism::unpack_suit;
+use crate::poker::{Card, Hand};
+use std::collections::HashMap;
+
+/// Most betting rounds an indexer supports.
+pub const MAX_ROUNDS: usize = 8;
+const SUITS: usize = SUIT_COUNT as usize;
+
+/// Cards each suit holds in each round.
+type Counts = [[u8; MAX_ROUNDS]; SUITS];
+
+/// One way of spreading a round's cards over the suits, suits sorted from
+/// most to fewest cards.
+#[derive(Debug, Clone, PartialEq)]
+struct Configuration {
+    counts: Counts,
+    /// Suit ranges `start..end` holding equal counts.
+    groups: Vec<(usize, usize)>,
+    /// Multisets of suit choices per group.
+    group_size: Vec<u64>,
+    offset: u64,
+}
+
+/// Perfect index of hands modulo suit permutations, after Waugh's hand
+/// isomorphism: cards are dealt over rounds (`[2, 3, 1, 1]` for hold'em
+/// hole, flop, turn and river) and each round's index is dense in
+/// `0..size(round)`. Suit permutations and card order within a round do not
+/// change the index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandIndexer {
+    cards_per_round: Vec<u32>,
+    configurations: Vec<Vec<Configuration>>,
+    lookup: Vec<HashMap<Counts, usize>>,
+    sizes: Vec<u64>,
+}
+
+fn suit_tuples(rounds: usize, remaining: &[u8], below: &[u8], out: &mut Vec<[u8; MAX_ROUNDS]>) {
+    // every tuple, lexicographically at most `below`, fitting in `remaining`
+    #[allow(clippy::too_many_arguments)]
+    fn go(
+        r: usize,
+        rounds: usize,
+        remaining: &[u8],
+        below: &[u8],
+        tight: bool,
+        used: u32,
+        cur: &mut [u8; MAX_ROUNDS],
+        out: &mut Vec<[u8; MAX_ROUNDS]>,
+    ) {
+        if r == rounds {
+            out.push(*cur);
+            return;
+        }
+        let top = if tight {
+            remaining[r].min(below[r])
+        } else {
+            remaining[r]
+        };
+        for n in (0..=top).rev() {
+            if used + n as u32 > 13 {
+                continue;
+            }
+            cur[r] = n;
+            go(
+                r + 1,
+                rounds,
+                remaining,
+                below,
+                tight && n == below[r],
+                used + n as u32,
+                cur,
+                out,
+            );
+        }
+        cur[r] = 0;
+    }
+    go(
+        0,
+        rounds,
+        remaining,
+        below,
+        true,
+        0,
+        &mut [0; MAX_ROUNDS],
+        out,
+    );
+}
+
+fn configurations(cards: &[u32]) -> Vec<Counts> {
+    fn go(
+        suit: usize,
+        rounds: usize,
+        remaining: [u8; MAX_ROUNDS],
+        cur: &mut Counts,
+        out: &mut Vec<Counts>,
+    ) {
+        if suit == SUITS {
+            if remaining.iter().all(|&n| n == 0) {
+                out.push(*cur);
+            }
+            return;
+        }
+        let below = if suit == 0 {
+            [13; MAX_ROUNDS]
+        } else {
+            cur[suit - 1]
+        };
+        let mut tuples = Vec::new();
+        suit_tuples(rounds, &remaining, &below, &mut tuples);
+        for t in tuples {
+            let mut rest = remaining;
+            for r in 0..rounds {
+                rest[r] -= t[r];
+            }
+            cur[suit] = t;
+            go(suit + 1, rounds, rest, cur, out);
+        }
+        cur[suit] = [0; MAX_ROUNDS];
+    }
+    let mut remaining = [0u8; MAX_ROUNDS];
+    for (r, &n) in cards.iter().enumerate() {
+        remaining[r] = n as u8;
+    }
+    let mut out = Vec::new();
+    go(
+        0,
+        cards.len(),
+        remaining,
+        &mut [[0; MAX_ROUNDS]; SUITS],
+        &mut out,
+    );
+    out
+}
+
+/// Rank choices of one suit holding `counts` cards over the rounds.
+fn suit_size(counts: &[u8]) -> u128 {
+    let mut left = 13;
+    let mut out = 1u128;
+    for &n in counts {
+        out *= binom(left, n as u32);
+        left -= n as u32;
+    }
+    out
+}
+
+/// Colex index of `ranks` among the ranks not in `used`.
+fn rank_set_index(ranks: u16, used: u16) -> u64 {
+    let mut out = 0u128;
+    let mut i = 0;
+    for r in 0..13 {
+        if ranks & (1 << r) != 0 {
+            let pos = (0..r).filter(|&x| used & (1 << x) == 0).count() as u32;
+            i += 1;
+            out += binom(pos, i);
+        }
+    }
+    out as u64
+}
+
+fn rank_set_unindex(mut index: u64, n: u32, used: u16) -> u16 {
+    let free: Vec<u32> = (0..13).filter(|&x| used & (1 << x) == 0).collect();
+    let mut out = 0u16;
+    for i in (1..=n).rev() {
+        let mut p = i - 1;
+        while binom(p + 1, i) as u64 <= index {
+            p += 1;
+        }
+        index -= binom(p, i) as u64;
+        out |= 1 << free[p as usize];
+    }
+    out
+}
+
+/// Rank of a non-increasing sequence among multisets of its length.
+fn multiset_index(values: &[u64]) -> u128 {
+    let k = values.len() as u32;
+    values
+        .iter()
+        .enumerate()
+        .map(|(j, &v)| binom(v as u32 + k - 1 - j as u32, k - j as u32))
+        .sum()
+}
+
+fn multiset_unindex(mut index: u128, k: u32) -> Vec<u64> {
+    let mut out = Vec::with_capacity(k as usize);
+    for j in 0..k {
+        let m = k - j;
+        // largest w with binom(w, m) <= index, by doubling then bisecting
+        let mut hi = m;
+        while binom(hi, m) <= index {
+            hi *= 2;
+        }
+        let mut w = m - 1;
+        while hi - w > 1 {
+            let mid = (w + hi) / 2;
+            if binom(mid, m) <= index {
+                w = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        index -= binom(w, m);
+        out.push((w - (m - 1)) as u64);
+    }
+    out
+}
+
+impl HandIndexer {
+    /// Indexer for cards dealt `cards_per_round` at a time.
+    pub fn new(cards_per_round: &[u32]) -> Result<Self, String> {
+        if cards_per_round.is_empty() || cards_per_round.len() > MAX_ROUNDS {
+            return Err(format!("need 1-{} rounds", MAX_ROUNDS));
+        }
+        if cards_per_round.iter().sum::<u32>() > 52 {
+            return Err("more than 52 cards".into());
+        }
+        let mut configurations_out = Vec::new();
+        let mut lookup = Vec::new();
+        let mut sizes = Vec::new();
+        for round in 0..cards_per_round.len() {
+            let mut configs = Vec::new();
+            let mut map = HashMap::new();
+            let mut offset = 0u128;
+            for counts in configurations(&cards_per_round[..=round]) {
+                let mut groups = Vec::new();
+                let mut start = 0;
+                for s in 1..=SUITS {
+                    if s == SUITS || counts[s] != counts[start] {
+                        groups.push((start, s));
+                        start = s;
+                    }
+                }
+                let mut group_sizes = Vec::new();
+                let mut size = 1u128;
+                for &(a, b) in &groups {
+                    let n = suit_size(&counts[a][..=round]);
+                    let g = binom((n + (b - a) as u128 - 1) as u32, (b - a) as u32);
+                    group_sizes.push(g as u64);
+                    size *= g;
+                }
+                map.insert(counts, configs.len());
+                configs.push(Configuration {
+                    counts,
+                    groups,
+                    group_size: group_sizes,
+                    offset: offset as u64,
+                });
+                offset += size;
+            }
+            if offset > u64::MAX as u128 {
+                return Err("index does not fit in 64 bits".into());
+            }
+            configurations_out.push(configs);
+            lookup.push(map);
+            sizes.push(offset as u64);
+        }
+        Ok(Self {
+            cards_per_round: cards_per_round.to_vec(),
+            configurations: configurations_out,
+            lookup,
+            sizes,
+        })
+    }
+
+    /// Hold'em: hole cards, flop, turn, river.
+    pub fn holdem() -> Self {
+        Self::new(&[2, 3, 1, 1]).unwrap()
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.cards_per_round.len()
+    }
+
+    pub fn cards_per_round(&self) -> &[u32] {
+        &self.cards_per_round
+    }
+
+    /// Distinct hands up to and including `round`.
+    pub fn size(&self, round: usize) -> u64 {
+        self.sizes[round]
+    }
+
+    /// Index of the hand dealt as one mask per round; the index belongs to
+    /// the last round given.
+    pub fn index(&self, rounds: &[u64]) -> Result<u64, String> {
+        if rounds.is_empty() || rounds.len() > self.rounds() {
+            return Err(format!("need 1-{} rounds of cards", self.rounds()));
+        }
+        let round = rounds.len() - 1;
+        let mut seen = 0u64;
+        for (r, &m) in rounds.iter().enumerate() {
+            if m.count_ones() != self.cards_per_round[r] {
+                return Err(format!(
+                    "round {} needs {} cards",
+                    r, self.cards_per_round[r]
+                ));
+            }
+            if m & seen != 0 || m >> 52 != 0 {
+                return Err("cards repeat or are not in the deck".into());
+            }
+            seen |= m;
+        }
+
+        let mut suits: Vec<([u8; MAX_ROUNDS], u64)> = (0..SUIT_COUNT)
+            .map(|s| {
+                let mut counts = [0u8; MAX_ROUNDS];
+                let mut used = 0u16;
+                let mut index = 0u64;
+                for (r, &m) in rounds.iter().enumerate() {
+                    let ranks = pack_suit(m, s);
+                    let n = ranks.count_ones();
+                    counts[r] = n as u8;
+                    index = index * binom(13 - used.count_ones(), n) as u64
+                        + rank_set_index(ranks, used);
+                    used |= ranks;
+                }
+                (counts, index)
+            })
+            .collect();
+        suits.sort_unstable_by(|a, b| b.cmp(a));
+        let mut counts = [[0u8; MAX_ROUNDS]; SUITS];
+        for (c, s) in counts.iter_mut().zip(&suits) {
+            *c = s.0;
+        }
+        let config = &self.configurations[round][self.lookup[round][&counts]];
+        let mut index = 0u128;
+        for (g, &(a, b)) in config.groups.iter().enumerate() {
+            let values: Vec<u64> = suits[a..b].iter().map(|s| s.1).collect();
+            index = index * config.group_size[g] as u128 + multiset_index(&values);
+        }
+        Ok(config.offset + index as u64)
+    }
+
+    /// A representative hand of `index` in `round`, one mask per round.
+    pub fn unindex(&self, round: usize, index: u64) -> Result<Vec<u64>, String> {
+        if round >= self.rounds() || index >= self.sizes[round] {
+            return Err("index out of range".into());
+        }
+        let configs = &self.configurations[round];
+        let c = configs.partition_point(|c| c.offset <= index) - 1;
+        let config = &configs[c];
+        let mut rest = index - config.offset;
+        let mut suit_index = [0u64; SUITS];
+        for (g, &(a, b)) in config.groups.iter().enumerate().rev() {
+            let size = config.group_size[g];
+            let values = multiset_unindex((rest % size) as u128, (b - a) as u32);
+            rest /= size;
+            suit_index[a..b].copy_from_slice(&values);
+        }
+
+        let mut out = vec![0u64; round + 1];
+        for (s, (counts, &suit)) in config.counts.iter().zip(&suit_index).enumerate() {
+            let counts = &counts[..=round];
+            let mut radix = Vec::with_capacity(round + 1);
+            let mut left = 13;
+            for &n in counts {
+                radix.push(binom(left, n as u32) as u64);
+                left -= n as u32;
+            }
+            let mut digits = vec![0u64; round + 1];
+            let mut idx = suit;
+            for r in (0..=round).rev() {
+                digits[r] = idx % radix[r];
+                idx /= radix[r];
+            }
+            let mut used = 0u16;
+            for r in 0..=round {
+                let ranks = rank_set_unindex(digits[r], counts[r] as u32, used);
+                out[r] |= unpack_suit(ranks, s as u32);
+                used |= ranks;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Index of `hole` with `board` cards in deal order, in the round the
+    /// board reaches.
+    pub fn index_hand(&self, hole: &Hand, board: &[Card]) -> Result<u64, String> {
+        let mut rounds = vec![hole.mask];
+        let mut dealt = 0;
+        for &n in &self.cards_per_round[1..] {
+            if dealt == board.len() {
+                break;
+            }
+            let end = dealt + n as usize;
+            if end > board.len() {
+                return Err("board stops in the middle of a round".into());
+            }
+            rounds.push(board[dealt..end].iter().fold(0, |m, c| m | c.mask()));
+            dealt = end;
+        }
+        if dealt != board.len() {
+            return Err("board has more cards than the rounds allow".into());
+        }
+        self.index(&rounds)
+    }
+
+    /// Representative hole and board, in deal order, of `index` in `round`.
+    pub fn unindex_hand(&self, round: usize, index: u64) -> Result<(Hand, Vec<Card>), String> {
+        let rounds = self.unindex(round, index)?;
+        let board = rounds[1..]
+            .iter()
+            .flat_map(|&m| Hand { mask: m }.cards().collect::<Vec<_>>())
+            .collect();
+        Ok((Hand { mask: rounds[0] }, board))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::deck::XorShift64;
+    use crate::poker::game::pick_random_board;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    /// Swap the card bits of suits `a` and `b`.
+    fn swap_suits(m: u64, a: u32, b: u32) -> u64 {
+        let (ra, rb) = (pack_suit(m, a), pack_suit(m, b));
+        let rest = m & !unpack_suit(ra, a) & !unpack_suit(rb, b);
+        rest | unpack_suit(ra, b) | unpack_suit(rb, a)
+    }
+
+    #[test]
+    fn holdem_sizes() {
+        let idx = HandIndexer::holdem();
+        assert_eq!(idx.size(0), 169);
+        assert_eq!(idx.size(1), 1_286_792);
+        assert_eq!(idx.size(2), 55_190_538);
+        assert_eq!(idx.size(3), 2_428_287_420);
+    }
+
+    #[test]
+    fn preflop_is_dense() {
+        let idx = HandIndexer::new(&[2]).unwrap();
+        let mut seen = [false; 169];
+        for i in 0..52 {
+            for j in (i + 1)..52 {
+                seen[idx.index(&[(1u64 << i) | (1u64 << j)]).unwrap() as usize] = true;
+            }
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn round_trip_and_isomorphism() {
+        let idx = HandIndexer::holdem();
+        let mut rng = XorShift64::new(9);
+        for _ in 0..500 {
+            let mut rounds = Vec::new();
+            let mut used = 0;
+            for &n in idx.cards_per_round() {
+                let m = pick_random_board(used, n, &mut rng);
+                used |= m;
+                rounds.push(m);
+                let i = idx.index(&rounds).unwrap();
+                let back = idx.unindex(rounds.len() - 1, i).unwrap();
+                assert_eq!(idx.index(&back).unwrap(), i);
+                let swapped: Vec<u64> = rounds.iter().map(|&m| swap_suits(m, 0, 3)).collect();
+                assert_eq!(idx.index(&swapped).unwrap(), i);
+            }
+            let river = rng.next_u64() % idx.size(3);
+            let hand = idx.unindex(3, river).unwrap();
+            assert_eq!(idx.index(&hand).unwrap(), river);
+        }
+    }
+
+    #[test]
+    fn rounds_are_kept_apart() {
+        let idx = HandIndexer::holdem();
+        let a = idx
+            .index(&[mask("AsKs"), mask("2s3s4h"), mask("5h")])
+            .unwrap();
+        let b = idx
+            .index(&[mask("AsKs"), mask("2s3s5h"), mask("4h")])
+            .unwrap();
+        assert_ne!(a, b);
+        let hole = Hand::from_string("AsKs");
+        let board: Vec<Card> = ["2s", "4h", "3s", "5h"]
+            .iter()
+            .map(|c| c.parse().unwrap())
+            .collect();
+        assert_eq!(idx.index_hand(&hole, &board).unwrap(), a);
+        let (h, cards) = idx.unindex_hand(2, a).unwrap();
+        assert_eq!(idx.index_hand(&h, &cards).unwrap(), a);
+        assert!(idx.index_hand(&hole, &board[..2]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_hands() {
+        let idx = HandIndexer::holdem();
+        assert!(idx.index(&[mask("As")]).is_err());
+        assert!(idx.index(&[mask("AsKs"), mask("As2c3c")]).is_err());
+        assert!(idx.unindex(0, 169).is_err());
+        assert!(HandIndexer::new(&[]).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+use crate::poker::hand::SUIT_COUNT;
+
+/// Spread a 13-bit rank mask back onto suit `s` of a card mask.
+pub(crate) fn unpack_suit(ranks: u16, s: u32) -> u64 {
+    let mut out = 0u64;
+    for r in 0..13 {
+        if ranks & (1 << r) != 0 {
+            out |= 1u64 << (r * SUIT_COUNT + s);
+        }
+    }
+    out
+}
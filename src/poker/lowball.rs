@@ -0,0 +1,229 @@
+use crate::poker::evaluator::{category, eval_with, CATEGORY_NAMES};
+use crate::poker::omaha::for_each_subset;
+use crate::poker::range::RANKS;
+use crate::poker::rules::GameRules;
+use std::ops::RangeInclusive;
+
+/// Low hand rankings. Both keep the `eval` contract: a higher u32 is the
+/// better hand, so they plug into the same comparison and tally code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LowballRules {
+    /// Razz style: aces low, straights and flushes ignored.
+    AceToFive,
+    /// Kansas City style: aces high, straights and flushes count against you.
+    DeuceToSeven,
+}
+
+const A5_NAMES: [&str; 6] = [
+    "",
+    "Pair",
+    "Two pair",
+    "Three of a kind",
+    "Full house",
+    "Four of a kind",
+];
+
+/// Ace-low value of rank index `r`: ace 1, deuce 2, ..., king 13.
+fn low_value(r: u32) -> u32 {
+    if r == 12 {
+        1
+    } else {
+        r + 2
+    }
+}
+
+fn low_char(v: u32) -> char {
+    if v == 1 {
+        'A'
+    } else {
+        RANKS[v as usize - 2] as char
+    }
+}
+
+/// A-5 badness of exactly five cards: shape (no pair, pair, ...) then ranks
+/// grouped by count, highest first. Lower is better.
+fn ace_to_five_badness(mask: u64) -> u32 {
+    let mut counts = [0u32; 14];
+    for r in 0..13u32 {
+        counts[low_value(r) as usize] += ((mask >> (r * 4)) & 0xF).count_ones();
+    }
+    let mut groups: Vec<(u32, u32)> = (1..14u32)
+        .filter(|&v| counts[v as usize] > 0)
+        .map(|v| (counts[v as usize], v))
+        .collect();
+    groups.sort_unstable_by(|a, b| b.cmp(a));
+    let shape = match (groups[0].0, groups.get(1).map_or(0, |g| g.0)) {
+        (1, _) => 0,
+        (2, 2) => 2,
+        (2, _) => 1,
+        (3, 2) => 4,
+        (3, _) => 3,
+        _ => 5,
+    };
+    let mut out = shape;
+    for &(n, v) in groups.iter() {
+        for _ in 0..n {
+            out = (out << 4) | v;
+        }
+    }
+    out
+}
+
+fn no_wheel_straight(rmask: u16) -> i32 {
+    let s = rmask & (rmask >> 1) & (rmask >> 2) & (rmask >> 3) & (rmask >> 4);
+    if s != 0 {
+        return 15 - s.leading_zeros() as i32 + 4;
+    }
+    -1
+}
+
+/// Score of a hand too short to make a low, below every real low.
+pub const NO_LOW: u32 = 0;
+
+/// Best five card low among the cards of `mask` (5 to 7 cards), `NO_LOW`
+/// with fewer than five.
+pub fn eval_lowball(mask: u64, rules: LowballRules) -> u32 {
+    if mask.count_ones() < 5 {
+        return NO_LOW;
+    }
+    let mut best = NO_LOW;
+    for_each_subset(mask, 5, &mut |five| {
+        let score = match rules {
+            LowballRules::AceToFive => !ace_to_five_badness(five),
+            LowballRules::DeuceToSeven => !eval_with(five, no_wheel_straight, false),
+        };
+        best = best.max(score);
+    });
+    best
+}
+
+/// Human readable low, e.g. "7-5 low" or "Pair of 3s".
+pub fn describe_lowball(score: u32, rules: LowballRules) -> String {
+    if score == NO_LOW {
+        return "No low".to_string();
+    }
+    let (shape, ranks): (usize, Vec<char>) = match rules {
+        LowballRules::AceToFive => {
+            let badness = !score;
+            let shape = (badness >> 20) as usize;
+            let ranks = (0..5)
+                .rev()
+                .map(|i| low_char((badness >> (i * 4)) & 0xF))
+                .collect();
+            (shape, ranks)
+        }
+        LowballRules::DeuceToSeven => {
+            let high = !score;
+            let cat = category(high);
+            let ranks = (0..5)
+                .rev()
+                .map(|i| RANKS[((high >> (i * 4)) & 0xF) as usize] as char)
+                .collect();
+            if cat != 0 {
+                return CATEGORY_NAMES[cat].to_string();
+            }
+            (0, ranks)
+        }
+    };
+    match shape {
+        0 => format!("{}-{} low", ranks[0], ranks[1]),
+        1 => format!("Pair of {}s", ranks[0]),
+        _ => A5_NAMES[shape].to_string(),
+    }
+}
+
+/// Showdown between complete lowball hands, e.g. final 2-7 draw hands or
+/// seven Razz cards. There is no board.
+#[derive(Debug, Clone, Copy)]
+pub struct Lowball(pub LowballRules);
+
+impl GameRules for Lowball {
+    fn hole_cards(&self) -> RangeInclusive<u32> {
+        5..=7
+    }
+
+    fn board_size(&self) -> u32 {
+        0
+    }
+
+    fn score(&self, hole: u64, _board: u64) -> u32 {
+        eval_lowball(hole, self.0)
+    }
+
+    fn cost(&self, hole_cards: u32) -> u128 {
+        [1, 6, 21][hole_cards as usize - 5]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    fn a5(s: &str) -> u32 {
+        eval_lowball(Hand::from_string(s).mask, LowballRules::AceToFive)
+    }
+
+    fn d7(s: &str) -> u32 {
+        eval_lowball(Hand::from_string(s).mask, LowballRules::DeuceToSeven)
+    }
+
+    #[test]
+    fn ace_to_five_order() {
+        assert!(a5("As2c3d4h5s") > a5("As2c3d4h6s"));
+        assert!(a5("7s5c4d3h2s") > a5("7s6c3d2hAs"));
+        assert!(a5("KsQcJdTh9s") > a5("AsAc2d3h4s"));
+        assert!(a5("AsAc2d3h4s") > a5("2s2c3d3hAs"));
+        assert_eq!(a5("As2s3s4s5s"), a5("As2c3d4h5d"));
+    }
+
+    #[test]
+    fn ace_to_five_best_of_seven() {
+        assert_eq!(a5("As2c3d4h5sKdKh"), a5("As2c3d4h5d"));
+        assert_eq!(a5("AsAc2d2h3s3d4c"), a5("As2d3s4cAc"));
+    }
+
+    #[test]
+    fn deuce_to_seven_order() {
+        assert!(d7("7s5c4d3h2s") > d7("7s6c4d3h2s"));
+        assert!(d7("7s5c4d3h2s") > d7("8s5c4d3h2s"));
+        assert!(d7("8s6c4d3h2s") > d7("6s5c4d3h2s"));
+        assert!(d7("As5c4d3h2s") < d7("Ks5c4d3h2s"));
+        assert!(d7("7s5s4s3s2s") < d7("KsQcJdTh8s"));
+        assert!(d7("KsQcJdTh8s") > d7("2s2c4d5h7s"));
+    }
+
+    #[test]
+    fn lowball_showdown() {
+        use crate::poker::{solve_equity, Range};
+        let hero = Hand::from_string("7s5c4d3h2s").mask;
+        let villains = [Range::from_omaha_notation("8s6c4c3c2c", 5).unwrap()];
+        let rules = Lowball(LowballRules::DeuceToSeven);
+        let r = solve_equity(&rules, hero, &villains, 0, 0, 1).unwrap();
+        assert_eq!(r.hero_win, 1.0);
+        let rules = Lowball(LowballRules::AceToFive);
+        let r = solve_equity(&rules, hero, &villains, 0, 0, 1).unwrap();
+        assert_eq!(r.hero_win, 1.0);
+    }
+
+    #[test]
+    fn descriptions() {
+        let a = LowballRules::AceToFive;
+        let d = LowballRules::DeuceToSeven;
+        assert_eq!(describe_lowball(a5("7s5c4d3hAs"), a), "7-5 low");
+        assert_eq!(describe_lowball(a5("As2c3d4h5s"), a), "5-4 low");
+        assert_eq!(describe_lowball(a5("3s3c4d5h6s"), a), "Pair of 3s");
+        assert_eq!(describe_lowball(a5("3s3c4d4h6s"), a), "Two pair");
+        assert_eq!(describe_lowball(d7("7s5c4d3h2s"), d), "7-5 low");
+        assert_eq!(describe_lowball(d7("As5c4d3h2s"), d), "A-5 low");
+        assert_eq!(describe_lowball(d7("6s5c4d3h2s"), d), "Straight");
+    }
+
+    #[test]
+    fn short_hands_make_no_low() {
+        assert_eq!(a5(""), NO_LOW);
+        assert_eq!(d7("7s5c4d3h"), NO_LOW);
+        assert!(a5("KsKcKdKhQs") > NO_LOW);
+        assert_eq!(describe_lowball(NO_LOW, LowballRules::AceToFive), "No low");
+    }
+}
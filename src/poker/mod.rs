@@ -1,7 +1,52 @@
+pub mod blockers;
+pub mod bucketing;
+pub mod card;
+pub mod cfr;
+pub mod deck;
+pub mod distribution;
 pub mod evaluator;
+pub mod formats;
 pub mod game;
+pub mod grid;
 pub mod hand;
+pub mod hilo;
+pub mod icm;
+pub mod indexer;
+pub mod isomorphism;
+pub mod lowball;
+pub mod narrowing;
+pub mod omaha;
+pub mod potential;
+pub mod pushfold;
 pub mod range;
-pub use game::{solve_ranges, EquityResult, Game};
+pub mod rules;
+pub mod shortdeck;
+pub mod shove;
+pub mod strategy;
+pub mod stud;
+pub mod tree;
+pub use blockers::{BlockerCount, BlockerReport};
+pub use bucketing::{build_buckets, emd, BucketConfig, BucketMethod, BucketTable};
+pub use card::Card;
+pub use cfr::{BestResponse, CfrAlgorithm, CfrSolver};
+pub use deck::Deck;
+pub use distribution::{equity_distributions, ComboEquity, EquityDistribution};
+pub use formats::RangeFormat;
+pub use game::{solve_equity, solve_ranges, EquityResult, Game};
+pub use grid::RangeGrid;
 pub use hand::Hand;
+pub use hilo::{solve_hilo, HiLo, HiLoGame, HiLoResult};
+pub use icm::{all_in_ev, icm, icm_exact, icm_monte_carlo, AllInEv};
+pub use indexer::HandIndexer;
+pub use lowball::{describe_lowball, eval_lowball, Lowball, LowballRules, NO_LOW};
+pub use narrowing::{ActionRule, HandFilter};
+pub use omaha::{eval_omaha, solve_omaha, Omaha};
+pub use potential::{hand_strength, HandStrength};
+pub use pushfold::{PreflopEquity, PushFold, PushFoldSolution};
 pub use range::Range;
+pub use rules::{GameRules, Holdem};
+pub use shortdeck::{eval_short_deck, solve_short_deck, ShortDeck, ShortDeckRules};
+pub use shove::{class_ranking, top_range, ShoveDecision, ShoveSpot};
+pub use strategy::{NodeStrategy, StrategyFile};
+pub use stud::{solve_stud, StudPlayer};
+pub use tree::{Action, ActionTree, Node, NodeKind, StreetSizes, TreeConfig};
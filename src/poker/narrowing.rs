@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::poker::blockers::{has_flush_draw, has_straight_draw};
+use crate::poker::evaluator::{category, eval, CATEGORY_NAMES};
+use crate::poker::range::Combo;
+use crate::poker::strategy::{NodeStrategy, StrategyFile};
+use crate::poker::tree::NodeKind;
+use crate::poker::Range;
+
+/// Other names accepted for made hand categories in rules.
+const CATEGORY_ALIASES: [(&str, usize); 8] = [
+    ("pairs", 1),
+    ("set", 3),
+    ("sets", 3),
+    ("trips", 3),
+    ("straights", 4),
+    ("flushes", 5),
+    ("boat", 6),
+    ("quads", 7),
+];
+
+/// Hands a rule clause applies to on a given board.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandFilter {
+    /// Made hands of at least this category, 0 (high card) to 8 (straight
+    /// flush), that the hole cards improve on the board's own category.
+    AtLeast(usize),
+    /// Four to a flush with a card to come, flushes excluded.
+    FlushDraw,
+    /// More straight outs than the board gives, straights and better
+    /// excluded, with a card to come.
+    StraightDraw,
+    /// Combos of a range.
+    Combos(Range),
+}
+
+impl HandFilter {
+    pub fn matches(&self, combo: u64, board: u64) -> bool {
+        let to_come = board.count_ones() < 5;
+        match self {
+            HandFilter::AtLeast(c) => {
+                let made = category(eval(combo | board));
+                made >= *c && (*c == 0 || made > category(eval(board)))
+            }
+            HandFilter::FlushDraw => {
+                to_come && category(eval(combo | board)) < 5 && has_flush_draw(combo, board)
+            }
+            HandFilter::StraightDraw => {
+                to_come && category(eval(combo | board)) < 4 && has_straight_draw(combo, board)
+            }
+            HandFilter::Combos(r) => r.combos.iter().any(|c| c.mask == combo),
+        }
+    }
+}
+
+impl FromStr for HandFilter {
+    type Err = String;
+
+    /// A category with a trailing `+` ("sets+", "two pair+"), "flush
+    /// draws", "straight draws", or range notation.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let lower = s.trim().to_lowercase();
+        if let Some(name) = lower.strip_suffix('+') {
+            let name = name.trim();
+            return CATEGORY_NAMES
+                .iter()
+                .position(|c| c.to_lowercase() == name)
+                .or_else(|| {
+                    CATEGORY_ALIASES
+                        .iter()
+                        .find(|(alias, _)| *alias == name)
+                        .map(|&(_, c)| c)
+                })
+                .map(HandFilter::AtLeast)
+                .ok_or_else(|| format!("unknown hand category {:?}", name));
+        }
+        match lower.trim_end_matches('s') {
+            "flush draw" => Ok(HandFilter::FlushDraw),
+            "straight draw" => Ok(HandFilter::StraightDraw),
+            _ => Range::parse(s).map(HandFilter::Combos),
+        }
+    }
+}
+
+/// User defined odds of an action: the probability of the first clause
+/// whose filter matches, `otherwise` when none does.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ActionRule {
+    pub clauses: Vec<(HandFilter, f64)>,
+    pub otherwise: f64,
+}
+
+impl ActionRule {
+    pub fn likelihood(&self, combo: u64, board: u64) -> f64 {
+        self.clauses
+            .iter()
+            .find(|(f, _)| f.matches(combo, board))
+            .map_or(self.otherwise, |&(_, p)| p)
+    }
+}
+
+impl FromStr for ActionRule {
+    type Err = String;
+
+    /// Clauses separated by `;`, each a `HandFilter` and an optional
+    /// percentage (100% when left out); "rest" sets what the other hands
+    /// do. For example "sets+; flush draws 50%; AK 25%; rest 5%".
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut rule = ActionRule::default();
+        for clause in s.split(';').map(str::trim).filter(|c| !c.is_empty()) {
+            let (filter, percent) = match clause.rsplit_once(' ') {
+                Some((f, p)) if p.ends_with('%') => (f.trim(), p),
+                _ => (clause, "100%"),
+            };
+            let p: f64 = percent
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| format!("bad percentage {:?}", percent))?;
+            if !(0.0..=100.0).contains(&p) {
+                return Err(format!("percentage out of range {:?}", percent));
+            }
+            if filter.eq_ignore_ascii_case("rest") {
+                rule.otherwise = p / 100.0;
+            } else {
+                rule.clauses.push((filter.parse()?, p / 100.0));
+            }
+        }
+        Ok(rule)
+    }
+}
+
+impl Range {
+    /// Posterior after seeing an action taken with probability
+    /// `likelihood(combo)`: each weight is multiplied by it and combos left
+    /// at 0 are dropped. Weights stay the share of each combo still in
+    /// range rather than being rescaled.
+    pub fn narrow(&self, likelihood: impl Fn(u64) -> f64) -> Result<Range, String> {
+        let mut combos = Vec::with_capacity(self.combos.len());
+        for c in &self.combos {
+            let p = likelihood(c.mask);
+            if !(0.0..=1.0).contains(&p) {
+                return Err(format!("likelihood {} is not a probability", p));
+            }
+            let weight = c.weight * p as f32;
+            if weight > 0.0 {
+                combos.push(Combo::with_weight(c.mask, weight));
+            }
+        }
+        if combos.is_empty() {
+            return Err("no combo in range takes this action".into());
+        }
+        Ok(Range { combos })
+    }
+
+    /// `narrow` by a rule on `board`, dropping combos the board blocks.
+    pub fn narrow_by_rule(&self, rule: &ActionRule, board: u64) -> Result<Range, String> {
+        self.narrow(|mask| {
+            if mask & board != 0 {
+                0.0
+            } else {
+                rule.likelihood(mask, board)
+            }
+        })
+    }
+
+    /// `narrow` by how often a solved strategy takes `action`. Combos the
+    /// strategy does not cover are dropped.
+    pub fn narrow_by_strategy(
+        &self,
+        strategy: &NodeStrategy,
+        action: usize,
+    ) -> Result<Range, String> {
+        let row = strategy
+            .frequencies
+            .get(action)
+            .ok_or_else(|| format!("no action {}", action))?;
+        let odds: HashMap<u64, f64> = strategy
+            .combos
+            .iter()
+            .zip(row)
+            .map(|(c, &f)| (c.mask, f.clamp(0.0, 1.0)))
+            .collect();
+        self.narrow(|mask| odds.get(&mask).copied().unwrap_or(0.0))
+    }
+
+    /// `narrow_by_strategy` at every node of `path` (as in
+    /// `StrategyFile::navigate`) where `player` acts, through the action
+    /// they took there.
+    pub fn narrow_along(
+        &self,
+        file: &StrategyFile,
+        player: usize,
+        path: &str,
+    ) -> Result<Range, String> {
+        let steps: Vec<&str> = path
+            .split('>')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        let mut range = self.clone();
+        for i in 0..steps.len() {
+            let (node, dealt) = file.navigate(&steps[..i].join(">"))?;
+            let (child, _) = file.navigate(&steps[..=i].join(">"))?;
+            if let NodeKind::Action {
+                player: actor,
+                children,
+                ..
+            } = &file.tree.nodes[node].kind
+            {
+                if *actor == player {
+                    let action = children.iter().position(|&c| c == child).unwrap();
+                    range = range.narrow_by_strategy(&file.strategy(node, &dealt)?, action)?;
+                }
+            }
+        }
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::cfr::tests::{pot_bet_only, sets_and_air};
+    use crate::poker::game::solve_ranges;
+    use crate::poker::strategy::StrategyFile;
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    fn weight(r: &Range, combo: &str) -> f32 {
+        r.combos
+            .iter()
+            .find(|c| c.mask == mask(combo))
+            .map_or(0.0, |c| c.weight)
+    }
+
+    #[test]
+    fn narrow_multiplies_weights() {
+        let prior = Range::parse("AA,KK:0.5,QQ").unwrap();
+        let posterior = prior
+            .narrow(|m| if m & mask("Ah") != 0 { 0.0 } else { 0.5 })
+            .unwrap();
+        assert_eq!(weight(&posterior, "AsAd"), 0.5);
+        assert_eq!(weight(&posterior, "AsAh"), 0.0);
+        assert_eq!(weight(&posterior, "KsKd"), 0.25);
+        assert_eq!(posterior.len(), 6 - 3 + 6 + 6);
+        assert!(prior.narrow(|_| 1.5).is_err());
+        assert!(prior.narrow(|_| f64::NAN).is_err());
+        assert!(prior.narrow(|_| 0.0).is_err());
+    }
+
+    #[test]
+    fn rules_from_text() {
+        let rule: ActionRule = "sets+; flush draws 50%; AQo 25%; rest 10%".parse().unwrap();
+        assert_eq!(rule.clauses.len(), 3);
+        assert_eq!(rule.clauses[0], (HandFilter::AtLeast(3), 1.0));
+        assert_eq!(rule.otherwise, 0.1);
+        let board = mask("Ks9s6c");
+        assert_eq!(rule.likelihood(mask("9h9d"), board), 1.0);
+        assert_eq!(rule.likelihood(mask("KhKd"), board), 1.0);
+        assert_eq!(rule.likelihood(mask("AsQs"), board), 0.5);
+        assert_eq!(rule.likelihood(mask("AhQd"), board), 0.25);
+        assert_eq!(rule.likelihood(mask("8h7d"), board), 0.1);
+        // a flush is no longer a draw
+        assert_eq!(rule.likelihood(mask("AsQs"), board | mask("2s")), 1.0);
+        let named: ActionRule = "Two pair+; straight draw".parse().unwrap();
+        assert_eq!(named.clauses[0].0, HandFilter::AtLeast(2));
+        assert_eq!(named.clauses[1].0, HandFilter::StraightDraw);
+        assert_eq!(named.otherwise, 0.0);
+    }
+
+    #[test]
+    fn board_made_hands_do_not_count() {
+        let pairs: HandFilter = "pairs+".parse().unwrap();
+        let paired = mask("KsKd6c");
+        // the board's pair is everyone's
+        assert!(!pairs.matches(mask("AhQd"), paired));
+        assert!(pairs.matches(mask("AhAd"), paired));
+        assert!(pairs.matches(mask("6h2d"), paired));
+        let sets: HandFilter = "sets+".parse().unwrap();
+        assert!(sets.matches(mask("KhQd"), paired));
+        assert!(!sets.matches(mask("AhQd"), mask("KsKdKc")));
+        assert!(sets.matches(mask("6h6d"), mask("KsKdKc")));
+        assert!(!"straights+"
+            .parse::<HandFilter>()
+            .unwrap()
+            .matches(mask("2h2d"), mask("9sTcJdQhKc")));
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert!("wheels+".parse::<ActionRule>().is_err());
+        assert!("sets+ 150%".parse::<ActionRule>().is_err());
+        assert!("sets+ x%".parse::<ActionRule>().is_err());
+        assert!("pair+; XYZ 50%".parse::<ActionRule>().is_err());
+    }
+
+    #[test]
+    fn chained_over_streets() {
+        let prior = Range::parse("TT+,98s,AsQs,AKo").unwrap();
+        let flop = mask("Ks9s6c");
+        let raise: ActionRule = "sets+; flush draws 50%".parse().unwrap();
+        let after_flop = prior.narrow_by_rule(&raise, flop).unwrap();
+        // KK loses the combos holding the board's king
+        assert_eq!(weight(&after_flop, "KhKd"), 1.0);
+        assert_eq!(weight(&after_flop, "KsKh"), 0.0);
+        assert_eq!(weight(&after_flop, "AsQs"), 0.5);
+        assert_eq!(weight(&after_flop, "AhAd"), 0.0);
+
+        let turn = flop | mask("2h");
+        let barrel: ActionRule = "sets+; rest 50%".parse().unwrap();
+        let after_turn = after_flop.narrow_by_rule(&barrel, turn).unwrap();
+        assert_eq!(weight(&after_turn, "AsQs"), 0.25);
+        assert_eq!(after_turn.combo_count(0), 3.0 + 0.25);
+
+        let r = solve_ranges(mask("AhAd"), &[after_turn], turn, 0, 1).unwrap();
+        assert!(r.hero_lose > 0.8, "aces against sets {}", r.hero_lose);
+    }
+
+    #[test]
+    fn narrowed_by_solved_strategy() {
+        let mut solver = sets_and_air(pot_bet_only());
+        let (oop, ip) = (
+            Range {
+                combos: solver.combos(0).to_vec(),
+            },
+            Range {
+                combos: solver.combos(1).to_vec(),
+            },
+        );
+        solver.solve(500);
+        let file = StrategyFile::from_solver(&solver);
+        let root = file.strategy(0, &[]).unwrap();
+        let bet = oop.narrow_by_strategy(&root, 1).unwrap();
+        // sets always bet, air bluffs half as often as value bets
+        assert!((weight(&bet, "9s9h") - 1.0).abs() < 0.05);
+        let bluffs: f64 = bet
+            .combos
+            .iter()
+            .filter(|c| !Hand { mask: c.mask }.to_string().contains('9'))
+            .map(|c| c.weight as f64)
+            .sum();
+        assert!((bluffs - 1.5).abs() < 0.2, "bluffs {}", bluffs);
+        assert!(oop.narrow_by_strategy(&root, 5).is_err());
+        assert_eq!(oop.narrow_along(&file, 0, "bet 100%").unwrap(), bet);
+        assert_eq!(oop.narrow_along(&file, 0, "").unwrap(), oop);
+        // IP's check back is left alone when narrowing OOP
+        let checked = oop.narrow_along(&file, 0, "check > check").unwrap();
+        let check = oop.narrow_by_strategy(&root, 0).unwrap();
+        assert_eq!(checked, check);
+        // the bluff catcher calls about half the time
+        let called = ip.narrow_along(&file, 1, "bet 100% > call").unwrap();
+        let share = called.combo_count(0) / ip.combo_count(file.board);
+        assert!((share - 0.5).abs() < 0.1, "calls {}", share);
+        assert!(oop.narrow_along(&file, 0, "raise").is_err());
+    }
+}
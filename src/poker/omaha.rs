@@ -0,0 +1,318 @@
+use crate::poker::evaluator::eval;
+use crate::poker::game::{binom, solve_equity, EquityResult};
+use crate::poker::range::{card_bit, rank_idx, Combo, SUITS};
+use crate::poker::rules::GameRules;
+use crate::poker::Range;
+use std::ops::RangeInclusive;
+
+/// Best high hand using exactly two cards from `hole` and three from `board`.
+pub fn eval_omaha(hole: u64, board: u64) -> u32 {
+    let mut best = 0;
+    for_each_subset(hole, 2, &mut |h| {
+        for_each_subset(board, 3, &mut |b| {
+            best = best.max(eval(h | b));
+        });
+    });
+    best
+}
+
+/// Calls `f` with every `k`-card subset of `mask`.
+pub(crate) fn for_each_subset<F: FnMut(u64)>(mask: u64, k: u32, f: &mut F) {
+    fn go<F: FnMut(u64)>(rest: u64, k: u32, picked: u64, f: &mut F) {
+        if k == 0 {
+            f(picked);
+            return;
+        }
+        if rest.count_ones() < k {
+            return;
+        }
+        let bit = rest & rest.wrapping_neg();
+        go(rest & !bit, k - 1, picked | bit, f);
+        go(rest & !bit, k, picked, f);
+    }
+    go(mask, k, 0, f);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SuitFilter {
+    Any,
+    DoubleSuited,
+    SingleSuited,
+    Rainbow,
+}
+
+fn suit_counts(mask: u64) -> [u32; 4] {
+    let mut out = [0; 4];
+    for (s, n) in out.iter_mut().enumerate() {
+        *n = (0..13)
+            .filter(|r| mask & (1u64 << (r * 4 + s)) != 0)
+            .count() as u32;
+    }
+    out
+}
+
+impl SuitFilter {
+    fn accepts(self, mask: u64) -> bool {
+        let counts = suit_counts(mask);
+        let paired = counts.iter().filter(|&&n| n >= 2).count();
+        match self {
+            SuitFilter::Any => true,
+            SuitFilter::DoubleSuited => counts.iter().filter(|&&n| n == 2).count() == 2,
+            SuitFilter::SingleSuited => paired == 1 && counts.iter().all(|&n| n <= 2),
+            SuitFilter::Rainbow => paired == 0,
+        }
+    }
+}
+
+fn explicit_hand(token: &[u8], hole_cards: u32) -> Option<u64> {
+    if token.len() != 2 * hole_cards as usize {
+        return None;
+    }
+    let mut mask = 0u64;
+    for pair in token.chunks(2) {
+        let r = rank_idx(pair[0])?;
+        let s = SUITS.iter().position(|&c| c == pair[1])? as u8;
+        mask |= card_bit(r, s);
+    }
+    if mask.count_ones() != hole_cards {
+        return None;
+    }
+    Some(mask)
+}
+
+fn split_filter(token: &str) -> (&str, SuitFilter) {
+    if let Some(head) = token.strip_suffix("ds") {
+        (head, SuitFilter::DoubleSuited)
+    } else if let Some(head) = token.strip_suffix("ss") {
+        (head, SuitFilter::SingleSuited)
+    } else if let Some(head) = token.strip_suffix('r') {
+        (head, SuitFilter::Rainbow)
+    } else {
+        (token, SuitFilter::Any)
+    }
+}
+
+/// Expand a rank pattern such as `AAxx` or `KQJTds`: fixed ranks take every
+/// suit choice, each `x` is any remaining card.
+fn pattern_hands(token: &str, hole_cards: u32) -> Result<Vec<u64>, String> {
+    let (pattern, filter) = split_filter(token);
+    if pattern.len() != hole_cards as usize {
+        return Err(format!(
+            "{} needs {} ranks: {}",
+            hole_cards, hole_cards, token
+        ));
+    }
+    let mut rank_counts = [0u32; 13];
+    let mut wild = 0;
+    for c in pattern.bytes() {
+        if c == b'x' {
+            wild += 1;
+        } else {
+            let r = rank_idx(c).ok_or_else(|| format!("bad rank: {}", c as char))?;
+            rank_counts[r as usize] += 1;
+        }
+    }
+    if rank_counts.iter().any(|&n| n > 4) {
+        return Err(format!("more than four of a rank: {}", token));
+    }
+    let mut fixed = vec![0u64];
+    for (r, &n) in rank_counts.iter().enumerate() {
+        if n == 0 {
+            continue;
+        }
+        let rank_mask = 0xFu64 << (r * 4);
+        let mut next = Vec::new();
+        for &m in fixed.iter() {
+            for_each_subset(rank_mask, n, &mut |s| next.push(m | s));
+        }
+        fixed = next;
+    }
+    let full = (1u64 << 52) - 1;
+    let mut out = Vec::new();
+    for m in fixed {
+        for_each_subset(full & !m, wild, &mut |w| {
+            if filter.accepts(m | w) {
+                out.push(m | w);
+            }
+        });
+    }
+    Ok(out)
+}
+
+impl Range {
+    /// Parse an Omaha range of `hole_cards` (4 or 5) card hands. Tokens are
+    /// comma separated and are either explicit hands (`AsKsQhJh`) or rank
+    /// patterns where `x` is any card (`AAxx`, `KQJT`), optionally followed
+    /// by `ds` (double suited), `ss` (single suited: one suited pair, no
+    /// other card sharing a suit) or `r` (rainbow).
+    pub fn from_omaha_notation(s: &str, hole_cards: u32) -> Result<Self, String> {
+        if !(4..=5).contains(&hole_cards) {
+            return Err("omaha hands have 4 or 5 cards".into());
+        }
+        let mut masks = Vec::new();
+        for tok in s.split(',') {
+            let tok = tok.trim();
+            if tok.is_empty() {
+                continue;
+            }
+            match explicit_hand(tok.as_bytes(), hole_cards) {
+                Some(m) => masks.push(m),
+                None => masks.extend(pattern_hands(tok, hole_cards)?),
+            }
+        }
+        masks.sort_unstable();
+        masks.dedup();
+        Ok(Self {
+            combos: masks.into_iter().map(Combo::new).collect(),
+        })
+    }
+
+    /// Every `hole_cards` card hand.
+    pub fn any_omaha(hole_cards: u32) -> Self {
+        let mut combos = Vec::with_capacity(binom(52, hole_cards) as usize);
+        for_each_subset((1u64 << 52) - 1, hole_cards, &mut |m| {
+            combos.push(Combo::new(m))
+        });
+        combos.sort_by_key(|c| c.mask);
+        Self { combos }
+    }
+}
+
+/// Pot-Limit Omaha with 4 or 5 hole cards: exactly two from hand, three
+/// from the board.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Omaha;
+
+impl GameRules for Omaha {
+    fn hole_cards(&self) -> RangeInclusive<u32> {
+        4..=5
+    }
+
+    fn score(&self, hole: u64, board: u64) -> u32 {
+        eval_omaha(hole, board)
+    }
+
+    fn cost(&self, hole_cards: u32) -> u128 {
+        binom(hole_cards, 2) * 10
+    }
+}
+
+/// Omaha equity of a 4 or 5 card `hero` hand against villain ranges of the
+/// same size, exact when cheap and Monte Carlo otherwise. `community` is
+/// empty preflop or holds 3-5 cards.
+pub fn solve_omaha(
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+) -> Result<EquityResult, String> {
+    solve_equity(&Omaha, hero, villains, community, max_iterations, seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::evaluator::category;
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    #[test]
+    fn two_from_hand() {
+        let board = mask("2h7h9hJhKc");
+        assert_eq!(category(eval_omaha(mask("AsKs3s4d"), board)), 1);
+        assert_eq!(category(eval_omaha(mask("AhQs3c4d"), board)), 0);
+        assert_eq!(category(eval_omaha(mask("Ah3hQs4d"), board)), 5);
+    }
+
+    #[test]
+    fn three_from_board() {
+        let board = mask("AsAcAdKsKc");
+        let score = eval_omaha(mask("2h3h4h5d"), board);
+        assert_eq!(category(score), 3);
+        assert_eq!(category(eval_omaha(mask("Kh2h3h4d"), board)), 3);
+        let full = eval_omaha(mask("QhQd2h3h"), board);
+        assert_eq!(category(full), 6);
+    }
+
+    #[test]
+    fn five_card_hand() {
+        let board = mask("AdKhKs2c3d");
+        let score = eval_omaha(mask("AhAs9c4d5d"), board);
+        assert_eq!(category(score), 6);
+    }
+
+    #[test]
+    fn notation_patterns() {
+        assert_eq!(Range::from_omaha_notation("AAKK", 4).unwrap().len(), 36);
+        assert_eq!(Range::from_omaha_notation("AAKKds", 4).unwrap().len(), 6);
+        assert_eq!(Range::from_omaha_notation("AsKsQhJh", 4).unwrap().len(), 1);
+        let aaxx = Range::from_omaha_notation("AAxx", 4).unwrap();
+        assert_eq!(aaxx.len(), 6 * 1128 + 4 * 48 + 1);
+        assert!(Range::from_omaha_notation("AAK", 4).is_err());
+        assert!(Range::from_omaha_notation("AAAAA", 5).is_err());
+        assert_eq!(Range::from_omaha_notation("AKQJT", 5).unwrap().len(), 1024);
+    }
+
+    #[test]
+    fn rainbow_and_single_suited() {
+        let r = Range::from_omaha_notation("AKQJr", 4).unwrap();
+        assert_eq!(r.len(), 24);
+        let ss = Range::from_omaha_notation("AKQJss", 4).unwrap();
+        let ds = Range::from_omaha_notation("AKQJds", 4).unwrap();
+        assert_eq!(ds.len(), 3 * 4 * 3);
+        // one suited pair, the other two cards in two other suits
+        assert_eq!(ss.len(), 6 * 4 * 3 * 2);
+        assert!(!ss.combos.iter().any(|c| c.mask == mask("AsKsQsJh")));
+        assert!(!ss.combos.iter().any(|c| c.mask == mask("AsKsQsJs")));
+        // three-flushes and monotone hands are neither
+        let (three_flush, monotone) = (4 * 4 * 3, 4);
+        assert_eq!(r.len() + ss.len() + ds.len() + three_flush + monotone, 256);
+    }
+
+    #[test]
+    fn any_counts() {
+        assert_eq!(Range::any_omaha(4).len(), 270725);
+    }
+
+    #[test]
+    fn revealed_river() {
+        let hero = mask("AhKh2c3c");
+        let villain = Range::from_omaha_notation("QsQdJsJd", 4).unwrap();
+        let board = mask("QhJh9h4s5s");
+        let r = solve_omaha(hero, &[villain], board, 0, 1).unwrap();
+        assert_eq!(r.iterations, 1);
+        assert_eq!(r.hero_win, 1.0);
+    }
+
+    #[test]
+    fn flop_equity_sums() {
+        let hero = mask("AsAhKsKh");
+        let villain = Range::from_omaha_notation("JcTc9d8d", 4).unwrap();
+        let board = mask("2c7d9c");
+        let r = solve_omaha(hero, &[villain], board, 0, 1).unwrap();
+        assert_eq!(r.iterations, 41 * 40 / 2);
+        assert!((r.hero_win + r.hero_tie + r.hero_lose - 1.0).abs() < 1e-9);
+        assert!(r.hero_win < 0.5);
+    }
+
+    #[test]
+    fn rejects_mismatched_sizes() {
+        let v = Range::from_notation("KK").unwrap();
+        assert!(solve_omaha(mask("AsAhKsKh"), &[v], mask("2c7d9c"), 0, 1).is_err());
+        assert!(solve_omaha(mask("AsAh"), &[Range::any()], mask("2c7d9c"), 0, 1).is_err());
+    }
+
+    #[test]
+    fn preflop_but_not_partial_flop() {
+        let hero = mask("AsAhKsKh");
+        let villain = Range::from_omaha_notation("7c6c5d4d", 4).unwrap();
+        let r = solve_omaha(hero, std::slice::from_ref(&villain), 0, 2000, 1).unwrap();
+        assert_eq!(r.iterations, 2000);
+        assert!(r.hero_win > 0.5 && r.hero_win < 0.75);
+        assert!(solve_omaha(hero, &[villain], mask("2c7d"), 0, 1).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::poker::deck::XorShift64;
+use crate::poker::evaluator::eval;
+use crate::poker::game::{binom, pick, pick_random_board, EXACT_WORK_LIMIT};
+use crate::poker::Range;
+
+const AHEAD: usize = 0;
+const TIED: usize = 1;
+const BEHIND: usize = 2;
+
+/// Hand strength metrics of a hero hand against one villain range.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HandStrength {
+    /// Runouts looked at.
+    pub iterations: u64,
+    /// Chance hero is ahead right now, ties counting half.
+    pub hs: f64,
+    /// Chance of getting ahead when currently behind or tied.
+    pub ppot: f64,
+    /// Chance of falling behind when currently ahead or tied.
+    pub npot: f64,
+    /// `hs * (1 - npot) + (1 - hs) * ppot`.
+    pub ehs: f64,
+    /// Mean of the squared hand strength after the lookahead cards.
+    pub ehs2: f64,
+}
+
+fn compare(hero: u32, villain: u32) -> usize {
+    match hero.cmp(&villain) {
+        std::cmp::Ordering::Greater => AHEAD,
+        std::cmp::Ordering::Equal => TIED,
+        std::cmp::Ordering::Less => BEHIND,
+    }
+}
+
+fn ratio(num: f64, den: f64) -> f64 {
+    if den > 0.0 {
+        num / den
+    } else {
+        0.0
+    }
+}
+
+/// Hand strength and potential of `hero` against `villain` on `board`,
+/// rolling out `lookahead` more board cards (1 for the next card, up to
+/// `5 - board` for the river). Every runout is enumerated when cheap;
+/// otherwise `max_iterations` random runouts are drawn (0 picks as many as
+/// the exact work budget allows).
+pub fn hand_strength(
+    hero: u64,
+    villain: &Range,
+    board: u64,
+    lookahead: u32,
+    max_iterations: u64,
+    seed: u64,
+) -> Result<HandStrength, String> {
+    if hero.count_ones() != 2 {
+        return Err("hero must have exactly 2 cards".into());
+    }
+    if hero & board != 0 {
+        return Err("community overlaps hero".into());
+    }
+    if board.count_ones() + lookahead > 5 {
+        return Err(format!("lookahead of {} runs past the river", lookahead));
+    }
+    let dead = hero | board;
+    let combos: Vec<(u64, f64, u32)> = villain
+        .live_combos(dead)
+        .filter(|c| c.weight > 0.0)
+        .map(|c| (c.mask, c.weight as f64, eval(c.mask | board)))
+        .collect();
+    if combos.is_empty() {
+        return Err("villain range has no live combos".into());
+    }
+    let hero_now = eval(hero | board);
+
+    let mut now = [0.0f64; 3];
+    for &(_, w, score) in &combos {
+        now[compare(hero_now, score)] += w;
+    }
+    let hs = ratio(now[AHEAD] + now[TIED] / 2.0, now.iter().sum());
+
+    let mut hp = [[0.0f64; 3]; 3];
+    let mut hs2 = 0.0;
+    let mut runouts = 0u64;
+    let mut visit = |add: u64| {
+        let full = board | add;
+        let hero_final = eval(hero | full);
+        let mut after = [0.0f64; 3];
+        for &(mask, w, score) in &combos {
+            if mask & add != 0 {
+                continue;
+            }
+            let to = compare(hero_final, eval(mask | full));
+            hp[compare(hero_now, score)][to] += w;
+            after[to] += w;
+        }
+        let total: f64 = after.iter().sum();
+        if total > 0.0 {
+            hs2 += ratio(after[AHEAD] + after[TIED] / 2.0, total).powi(2);
+            runouts += 1;
+        }
+    };
+
+    let free = 52 - dead.count_ones();
+    let work = binom(free, lookahead).saturating_mul(combos.len() as u128);
+    if work <= EXACT_WORK_LIMIT {
+        pick(dead, lookahead, 0, 0, &mut visit);
+    } else {
+        let target = if max_iterations == 0 {
+            (EXACT_WORK_LIMIT / combos.len() as u128).max(1) as u64
+        } else {
+            max_iterations
+        };
+        let mut rng = XorShift64::new(seed);
+        for _ in 0..target {
+            visit(pick_random_board(dead, lookahead, &mut rng));
+        }
+    }
+    if runouts == 0 {
+        return Err("no runout leaves a live villain combo".into());
+    }
+
+    let total = |row: usize| hp[row].iter().sum::<f64>();
+    let ppot = ratio(
+        hp[BEHIND][AHEAD] + hp[BEHIND][TIED] / 2.0 + hp[TIED][AHEAD] / 2.0,
+        total(BEHIND) + total(TIED) / 2.0,
+    );
+    let npot = ratio(
+        hp[AHEAD][BEHIND] + hp[TIED][BEHIND] / 2.0 + hp[AHEAD][TIED] / 2.0,
+        total(AHEAD) + total(TIED) / 2.0,
+    );
+    Ok(HandStrength {
+        iterations: runouts,
+        hs,
+        ppot,
+        npot,
+        ehs: hs * (1.0 - npot) + (1.0 - hs) * ppot,
+        ehs2: hs2 / runouts as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    #[test]
+    fn river_has_no_potential() {
+        let r = hand_strength(mask("AsAh"), &Range::any(), mask("AdAc2s7h9c"), 0, 0, 1).unwrap();
+        assert_eq!(r.iterations, 1);
+        assert_eq!(r.hs, 1.0);
+        assert_eq!((r.ppot, r.npot), (0.0, 0.0));
+        assert_eq!(r.ehs, 1.0);
+        assert_eq!(r.ehs2, 1.0);
+    }
+
+    #[test]
+    fn draw_versus_made_hand() {
+        let board = mask("Th9h2c");
+        let villain = Range::from_notation("AA,KK").unwrap();
+        let draw = hand_strength(mask("8h7h"), &villain, board, 1, 0, 1).unwrap();
+        assert_eq!(draw.hs, 0.0);
+        assert_eq!(draw.npot, 0.0);
+        assert!(draw.ppot > 0.25 && draw.ppot < 15.0 / 45.0);
+        let made = hand_strength(
+            mask("AsAd"),
+            &Range::from_notation("8h7h").unwrap(),
+            board,
+            1,
+            0,
+            1,
+        )
+        .unwrap();
+        assert_eq!(made.hs, 1.0);
+        // 9 hearts and the 6 offsuit jacks and sixes out of 45 unseen cards
+        assert!((made.npot - 15.0 / 45.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flop_to_river() {
+        let board = mask("3h4cJh");
+        let r = hand_strength(mask("AdQc"), &Range::any(), board, 2, 0, 1).unwrap();
+        assert_eq!(r.iterations, 47 * 46 / 2);
+        assert!(r.hs > 0.5 && r.hs < 0.7);
+        assert!(r.ppot > 0.0 && r.npot > 0.0);
+        assert!(r.ehs > 0.0 && r.ehs < 1.0);
+        assert!(r.ehs2 > 0.0 && r.ehs2 <= 1.0);
+    }
+
+    #[test]
+    fn preflop_samples() {
+        let r = hand_strength(mask("AsAh"), &Range::any(), 0, 5, 300, 7).unwrap();
+        assert_eq!(r.iterations, 300);
+        assert!((r.hs - 1224.5 / 1225.0).abs() < 1e-9);
+        assert!(r.ehs > 0.8 && r.ehs < 0.9);
+        assert!(r.ehs2 > 0.6);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let board = mask("3h4cJh");
+        assert!(hand_strength(mask("AdQc"), &Range::any(), board, 3, 0, 1).is_err());
+        assert!(hand_strength(mask("3hQc"), &Range::any(), board, 1, 0, 1).is_err());
+        let blocked = Range::from_notation("AdKd").unwrap();
+        assert!(hand_strength(mask("AdQc"), &blocked, board, 1, 0, 1).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::poker::game::sample_equity;
+use crate::poker::grid::{class_combos, RangeGrid, GRID_SIZE};
+use crate::poker::rules::Holdem;
+use crate::poker::Range;
+
+/// Number of starting hand classes, indexed `row * 13 + col` on the grid.
+pub const CLASSES: usize = GRID_SIZE * GRID_SIZE;
+
+/// All-in equity of every starting hand class against every other, with
+/// the number of combo pairs that can meet once cards are removed.
+#[derive(Debug, Clone)]
+pub struct PreflopEquity {
+    equity: Vec<f64>,
+    weight: Vec<f64>,
+}
+
+impl PreflopEquity {
+    /// Estimate every matchup with the equity engine, `samples` Monte Carlo
+    /// deals each. Every combo of a class is suit isomorphic to the others,
+    /// so one of them stands for the class against the other class's range.
+    pub fn estimate(samples: u64, seed: u64) -> Result<Self, String> {
+        if samples == 0 {
+            return Err("need at least one sample".into());
+        }
+        let classes: Vec<Range> = (0..CLASSES)
+            .map(|i| Range {
+                combos: class_combos(i / GRID_SIZE, i % GRID_SIZE),
+            })
+            .collect();
+        let mut equity = vec![0.5; CLASSES * CLASSES];
+        let mut weight = vec![0.0; CLASSES * CLASSES];
+        for a in 0..CLASSES {
+            let hero = classes[a].combos[0].mask;
+            for b in a..CLASSES {
+                let live = classes[b].live_combos(hero).count();
+                let pairs = (classes[a].combos.len() * live) as f64;
+                weight[a * CLASSES + b] = pairs;
+                weight[b * CLASSES + a] = pairs;
+                if live == 0 || a == b {
+                    continue;
+                }
+                let matchup = (a * CLASSES + b) as u64 + 1;
+                let r = sample_equity(
+                    &Holdem,
+                    hero,
+                    std::slice::from_ref(&classes[b]),
+                    0,
+                    samples,
+                    seed ^ matchup.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                )?;
+                let e = 1.0 - r.villain_equity[0];
+                equity[a * CLASSES + b] = e;
+                equity[b * CLASSES + a] = 1.0 - e;
+            }
+        }
+        Ok(Self { equity, weight })
+    }
+
+    /// Share of the pot class `a` wins all-in against class `b`.
+    pub fn equity(&self, a: usize, b: usize) -> f64 {
+        self.equity[a * CLASSES + b]
+    }
+
+    /// Combo pairs of class `a` against class `b` sharing no card.
+    pub fn weight(&self, a: usize, b: usize) -> f64 {
+        self.weight[a * CLASSES + b]
+    }
+}
+
+/// Heads-up push or fold: the small blind moves all-in or folds, the big
+/// blind calls or folds. Amounts are in big blinds. Spots with three or more
+/// players are not modelled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushFold {
+    /// Effective stack before posting blinds and antes.
+    pub stack: f64,
+    pub small_blind: f64,
+    pub big_blind: f64,
+    /// Ante posted by each player.
+    pub ante: f64,
+}
+
+impl Default for PushFold {
+    fn default() -> Self {
+        Self {
+            stack: 10.0,
+            small_blind: 0.5,
+            big_blind: 1.0,
+            ante: 0.0,
+        }
+    }
+}
+
+/// Equilibrium found by fictitious play, as the frequency each class jams
+/// from the small blind or calls from the big blind, in whole percents.
+#[derive(Debug, Clone)]
+pub struct PushFoldSolution {
+    pub iterations: u32,
+    /// Big blinds per hand the best responses gain over the average
+    /// strategies, averaged over both players.
+    pub exploitability: f64,
+    pub push: RangeGrid,
+    pub call: RangeGrid,
+}
+
+impl PushFoldSolution {
+    pub fn push_range(&self) -> Range {
+        Range::from_grid(&self.push)
+    }
+
+    pub fn call_range(&self) -> Range {
+        Range::from_grid(&self.call)
+    }
+}
+
+/// Frequencies rounded to whole percents, which drops what early best
+/// responses leave behind in the averages.
+fn to_grid(freq: &[f64]) -> RangeGrid {
+    let mut out = [[0.0f32; GRID_SIZE]; GRID_SIZE];
+    for (i, f) in freq.iter().enumerate() {
+        out[i / GRID_SIZE][i % GRID_SIZE] = ((f * 100.0).round() / 100.0) as f32;
+    }
+    RangeGrid::from_frequencies(&out)
+}
+
+impl PushFold {
+    /// Small blind's result per class when jamming against the big blind's
+    /// calling frequencies, averaged over the big blind's holdings.
+    fn push_values(&self, equity: &PreflopEquity, call: &[f64]) -> Vec<f64> {
+        let steal = self.big_blind + self.ante;
+        (0..CLASSES)
+            .map(|a| {
+                let (mut value, mut total) = (0.0, 0.0);
+                for (b, &c) in call.iter().enumerate() {
+                    let w = equity.weight(a, b);
+                    let showdown = (2.0 * equity.equity(a, b) - 1.0) * self.stack;
+                    value += w * (c * showdown + (1.0 - c) * steal);
+                    total += w;
+                }
+                value / total
+            })
+            .collect()
+    }
+
+    /// Big blind's result per class when calling a jam, and the weight of
+    /// jams it faces, given the small blind's jamming frequencies.
+    fn call_values(&self, equity: &PreflopEquity, push: &[f64]) -> Vec<(f64, f64)> {
+        (0..CLASSES)
+            .map(|b| {
+                let (mut value, mut total) = (0.0, 0.0);
+                for (a, &p) in push.iter().enumerate() {
+                    let w = equity.weight(b, a) * p;
+                    value += w * (2.0 * equity.equity(b, a) - 1.0) * self.stack;
+                    total += w;
+                }
+                (value, total)
+            })
+            .collect()
+    }
+
+    /// What each player's best response gains per hand over the average
+    /// strategies `push` and `call`.
+    fn gains(&self, equity: &PreflopEquity, push: &[f64], call: &[f64]) -> [f64; 2] {
+        let fold = -(self.small_blind + self.ante);
+        let bb_fold = -(self.big_blind + self.ante);
+        let push_values = self.push_values(equity, call);
+        let call_values = self.call_values(equity, push);
+        let (mut total, mut value, mut best_push, mut best_call) = (0.0, 0.0, 0.0, 0.0);
+        for a in 0..CLASSES {
+            let w: f64 = (0..CLASSES).map(|b| equity.weight(a, b)).sum();
+            value += w * (push[a] * push_values[a] + (1.0 - push[a]) * fold);
+            best_push += w * push_values[a].max(fold);
+            total += w;
+        }
+        for (b, &(v, faced)) in call_values.iter().enumerate() {
+            // the big blind collects the small blind's posting on a fold
+            let folded: f64 = (0..CLASSES)
+                .map(|a| equity.weight(b, a) * (1.0 - push[a]))
+                .sum();
+            best_call += -fold * folded + v.max(faced * bb_fold);
+        }
+        // the game is zero sum, so the big blind's value is -value
+        let value = value / total;
+        [best_push / total - value, best_call / total + value]
+    }
+
+    /// Run `iterations` rounds of fictitious play: each round both players
+    /// best respond to the other's average strategy, which then moves
+    /// towards that response.
+    pub fn solve(
+        &self,
+        equity: &PreflopEquity,
+        iterations: u32,
+    ) -> Result<PushFoldSolution, String> {
+        if self.small_blind < 0.0 || self.big_blind <= 0.0 || self.ante < 0.0 {
+            return Err("blinds and ante must be non-negative, the big blind positive".into());
+        }
+        if self.stack < self.big_blind + self.ante {
+            return Err("stack must cover the big blind and ante".into());
+        }
+        let fold = -(self.small_blind + self.ante);
+        let bb_fold = -(self.big_blind + self.ante);
+        let mut push = vec![1.0; CLASSES];
+        let mut call = vec![1.0; CLASSES];
+        for t in 1..=iterations {
+            let push_values = self.push_values(equity, &call);
+            let call_values = self.call_values(equity, &push);
+            let step = 1.0 / (t + 1) as f64;
+            for (p, v) in push.iter_mut().zip(&push_values) {
+                let best = if *v > fold { 1.0 } else { 0.0 };
+                *p += (best - *p) * step;
+            }
+            for (c, &(v, faced)) in call.iter_mut().zip(&call_values) {
+                let best = if v > faced * bb_fold { 1.0 } else { 0.0 };
+                *c += (best - *c) * step;
+            }
+        }
+        let gains = self.gains(equity, &push, &call);
+        Ok(PushFoldSolution {
+            iterations,
+            exploitability: (gains[0] + gains[1]) / 2.0,
+            push: to_grid(&push),
+            call: to_grid(&call),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::RangeFormat;
+    use std::sync::OnceLock;
+
+    fn equity() -> &'static PreflopEquity {
+        static EQUITY: OnceLock<PreflopEquity> = OnceLock::new();
+        EQUITY.get_or_init(|| PreflopEquity::estimate(64, 1).unwrap())
+    }
+
+    fn class(label: &str) -> usize {
+        (0..CLASSES)
+            .find(|&i| crate::poker::grid::class_label(i / GRID_SIZE, i % GRID_SIZE) == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn class_matchups() {
+        let e = equity();
+        let (aa, kk, aks) = (class("AA"), class("KK"), class("AKs"));
+        assert!((e.equity(aa, kk) - 0.82).abs() < 0.12);
+        assert!((e.equity(aa, kk) + e.equity(kk, aa) - 1.0).abs() < 1e-12);
+        assert_eq!(e.equity(aa, aa), 0.5);
+        // one AA combo is left once a player holds two aces
+        assert_eq!(e.weight(aa, aa), 6.0);
+        assert_eq!(e.weight(aa, aks), e.weight(aks, aa));
+        assert_eq!(e.weight(aa, aks), 6.0 * 2.0);
+    }
+
+    #[test]
+    fn short_stacks_jam_wide() {
+        let config = PushFold {
+            stack: 1.5,
+            ..PushFold::default()
+        };
+        let s = config.solve(equity(), 500).unwrap();
+        assert!(
+            s.push.frequency(12, 7) > 0.9,
+            "72o jams {}",
+            s.push.frequency(12, 7)
+        );
+        assert!(s.push_range().len() > 1200);
+    }
+
+    #[test]
+    fn deep_stacks_tighten_up() {
+        let config = PushFold {
+            stack: 15.0,
+            ..PushFold::default()
+        };
+        let s = config.solve(equity(), 1000).unwrap();
+        assert!(
+            s.exploitability < 0.02,
+            "exploitability {}",
+            s.exploitability
+        );
+        let (aa, seven_deuce) = ((0, 0), (7, 12));
+        assert_eq!(s.push.frequency(aa.0, aa.1), 1.0);
+        assert_eq!(s.call.frequency(aa.0, aa.1), 1.0);
+        assert!(s.push.frequency(seven_deuce.1, seven_deuce.0) < 0.01);
+        // the caller needs a stronger hand than the jammer
+        assert!(s.call.combo_count() < s.push.combo_count());
+        let notation = s.push_range().export(RangeFormat::Weighted);
+        assert!(notation.starts_with("AA"));
+    }
+
+    #[test]
+    fn fictitious_play_converges() {
+        let config = PushFold {
+            ante: 0.125,
+            ..PushFold::default()
+        };
+        let early = config.solve(equity(), 20).unwrap().exploitability;
+        let late = config.solve(equity(), 2000).unwrap().exploitability;
+        assert!(late < early, "{} then {}", early, late);
+        assert!(late >= 0.0);
+    }
+
+    #[test]
+    fn rejects_bad_config() {
+        let short = PushFold {
+            stack: 0.5,
+            ..PushFold::default()
+        };
+        assert!(short.solve(equity(), 1).is_err());
+        let negative = PushFold {
+            ante: -1.0,
+            ..PushFold::default()
+        };
+        assert!(negative.solve(equity(), 1).is_err());
+    }
+}
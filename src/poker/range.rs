@@ -1,7 +1,7 @@
 use crate::poker::hand::SUIT_COUNT;
 
-const RANKS: &[u8] = b"23456789TJQKA";
-const SUITS: &[u8] = b"scdh";
+pub(crate) const RANKS: &[u8] = b"23456789TJQKA";
+pub(crate) const SUITS: &[u8] = b"scdh";
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Combo {
@@ -18,20 +18,20 @@ impl Combo {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Range {
     pub combos: Vec<Combo>,
 }
 
-fn rank_idx(c: u8) -> Option<u8> {
+pub(crate) fn rank_idx(c: u8) -> Option<u8> {
     RANKS.iter().position(|&x| x == c).map(|p| p as u8)
 }
 
-fn card_bit(rank: u8, suit: u8) -> u64 {
+pub(crate) fn card_bit(rank: u8, suit: u8) -> u64 {
     1u64 << (rank as u32 * SUIT_COUNT + suit as u32)
 }
 
-fn pair_combos(rank: u8) -> Vec<Combo> {
+pub(crate) fn pair_combos(rank: u8) -> Vec<Combo> {
     let mut out = Vec::with_capacity(6);
     for s1 in 0..4u8 {
         for s2 in (s1 + 1)..4u8 {
@@ -42,7 +42,7 @@ fn pair_combos(rank: u8) -> Vec<Combo> {
     out
 }
 
-fn suited_combos(r1: u8, r2: u8) -> Vec<Combo> {
+pub(crate) fn suited_combos(r1: u8, r2: u8) -> Vec<Combo> {
     let mut out = Vec::with_capacity(4);
     for s in 0..4u8 {
         let m = card_bit(r1, s) | card_bit(r2, s);
@@ -51,7 +51,7 @@ fn suited_combos(r1: u8, r2: u8) -> Vec<Combo> {
     out
 }
 
-fn offsuit_combos(r1: u8, r2: u8) -> Vec<Combo> {
+pub(crate) fn offsuit_combos(r1: u8, r2: u8) -> Vec<Combo> {
     let mut out = Vec::with_capacity(12);
     for s1 in 0..4u8 {
         for s2 in 0..4u8 {
@@ -126,7 +126,7 @@ fn explicit_combo(token: &[u8]) -> Option<Combo> {
     Some(Combo::new(m))
 }
 
-fn parse_token(token: &str) -> Result<Vec<Combo>, String> {
+pub(crate) fn parse_token(token: &str) -> Result<Vec<Combo>, String> {
     let token = token.trim();
     if token.is_empty() {
         return Ok(vec![]);
@@ -0,0 +1,83 @@
+use crate::poker::evaluator::eval;
+use std::ops::RangeInclusive;
+
+/// Every card of a standard 52 card deck.
+pub const FULL_DECK: u64 = (1u64 << 52) - 1;
+
+/// What the equity engine needs to know about a poker variant: which cards
+/// are in play, how many each player holds, how big the board gets and who
+/// wins the pot at showdown.
+pub trait GameRules {
+    /// Cards in play, as a mask.
+    fn deck(&self) -> u64 {
+        FULL_DECK
+    }
+
+    /// Allowed hole card counts.
+    fn hole_cards(&self) -> RangeInclusive<u32>;
+
+    /// Community cards on a complete board.
+    fn board_size(&self) -> u32 {
+        5
+    }
+
+    /// Strength of a hole + board for the high (or only) pot, higher wins.
+    fn score(&self, hole: u64, board: u64) -> u32;
+
+    /// `eval` calls per `score`, which scales the budget for exact
+    /// enumeration.
+    fn cost(&self, _hole_cards: u32) -> u128 {
+        1
+    }
+
+    /// Fill `shares` with each player's fraction of the pot. The default
+    /// splits it evenly among the best scores.
+    fn split(&self, holes: &[u64], board: u64, shares: &mut [f64]) {
+        for (share, &h) in shares.iter_mut().zip(holes) {
+            *share = self.score(h, board) as f64;
+        }
+        split_best(shares);
+    }
+}
+
+/// Turn scores stored in `shares` into an even split among the maxima.
+pub(crate) fn split_best(shares: &mut [f64]) {
+    let best = shares.iter().cloned().fold(f64::MIN, f64::max);
+    let winners = shares.iter().filter(|&&s| s == best).count() as f64;
+    for s in shares.iter_mut() {
+        *s = if *s == best { 1.0 / winners } else { 0.0 };
+    }
+}
+
+/// No-limit / limit Texas Hold'em: two hole cards, best five of seven.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Holdem;
+
+impl GameRules for Holdem {
+    fn hole_cards(&self) -> RangeInclusive<u32> {
+        2..=2
+    }
+
+    fn score(&self, hole: u64, board: u64) -> u32 {
+        eval(hole | board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::Hand;
+
+    #[test]
+    fn default_split() {
+        let board = Hand::from_string("2c3d7h8s9s").mask;
+        let holes = [
+            Hand::from_string("AsAd").mask,
+            Hand::from_string("AhAc").mask,
+            Hand::from_string("KsKd").mask,
+        ];
+        let mut shares = [0.0; 3];
+        Holdem.split(&holes, board, &mut shares);
+        assert_eq!(shares, [0.5, 0.5, 0.0]);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::poker::deck::Deck;
+use crate::poker::evaluator::{category, eval_with};
+use crate::poker::game::{solve_equity, EquityResult};
+use crate::poker::range::Combo;
+use crate::poker::rules::{GameRules, FULL_DECK};
+use crate::poker::Range;
+use std::ops::RangeInclusive;
+
+pub const SHORT_DECK_SIZE: u32 = 36;
+/// Deuces through fives, removed from a short deck.
+pub const LOW_CARDS: u64 = 0xFFFF;
+
+/// Where three of a kind sits relative to a straight. Flushes beat full
+/// houses in both.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShortDeckRules {
+    #[default]
+    TripsBeatStraight,
+    Standard,
+}
+
+/// Standard straights plus A-6-7-8-9, the lowest one.
+fn short_straight_top(rmask: u16) -> i32 {
+    let s = rmask & (rmask >> 1) & (rmask >> 2) & (rmask >> 3) & (rmask >> 4);
+    if s != 0 {
+        return 15 - s.leading_zeros() as i32 + 4;
+    }
+    if (rmask & 0xF0) == 0xF0 && (rmask & (1 << 12)) != 0 {
+        return 7;
+    }
+    -1
+}
+
+/// Short-deck high hand. Scores keep the standard category bits (so
+/// `category` and `CATEGORY_NAMES` still apply) with the variant's order
+/// stacked on top, so a higher u32 is still the better hand.
+pub fn eval_short_deck(mask: u64, rules: ShortDeckRules) -> u32 {
+    let mut score = eval_with(mask, short_straight_top, true);
+    if rules == ShortDeckRules::TripsBeatStraight && category(score) == 4 {
+        // `eval_with` reads a straight before trips, the weaker hand here
+        let trips = eval_with(mask, |_| -1, true);
+        if category(trips) == 3 {
+            score = trips;
+        }
+    }
+    let order = match (category(score), rules) {
+        (3, ShortDeckRules::TripsBeatStraight) => 4,
+        (4, ShortDeckRules::TripsBeatStraight) => 3,
+        (5, _) => 6,
+        (6, _) => 5,
+        (c, _) => c as u32,
+    };
+    (order << 28) | score
+}
+
+fn check_cards(mask: u64, who: &str) -> Result<(), String> {
+    if mask & LOW_CARDS != 0 {
+        return Err(format!("{} holds a card below 6", who));
+    }
+    Ok(())
+}
+
+impl Range {
+    /// Hold'em notation restricted to ranks 6 through A.
+    pub fn from_short_deck_notation(s: &str) -> Result<Self, String> {
+        let range = Self::from_notation(s)?;
+        if range.combos.iter().any(|c| c.mask & LOW_CARDS != 0) {
+            return Err("short-deck ranges only use ranks 6+".into());
+        }
+        Ok(range)
+    }
+
+    /// Every two card combo of the 36 card deck.
+    pub fn any_short_deck() -> Self {
+        let mut combos = Vec::with_capacity(630);
+        for i in 16..52u8 {
+            for j in (i + 1)..52u8 {
+                combos.push(Combo::new((1u64 << i) | (1u64 << j)));
+            }
+        }
+        Self { combos }
+    }
+}
+
+impl Deck {
+    pub fn short_deck(seed: u64) -> Self {
+        Self::from_top(SHORT_DECK_SIZE, seed)
+    }
+}
+
+/// Short-deck hold'em: 36 cards, two hole cards, best five of seven.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ShortDeck(pub ShortDeckRules);
+
+impl GameRules for ShortDeck {
+    fn deck(&self) -> u64 {
+        FULL_DECK & !LOW_CARDS
+    }
+
+    fn hole_cards(&self) -> RangeInclusive<u32> {
+        2..=2
+    }
+
+    fn score(&self, hole: u64, board: u64) -> u32 {
+        eval_short_deck(hole | board, self.0)
+    }
+}
+
+/// Short-deck hold'em equity, exact when cheap and Monte Carlo otherwise.
+/// `community` is empty preflop or holds 3-5 cards.
+pub fn solve_short_deck(
+    hero: u64,
+    villains: &[Range],
+    community: u64,
+    max_iterations: u64,
+    seed: u64,
+    rules: ShortDeckRules,
+) -> Result<EquityResult, String> {
+    check_cards(hero, "hero")?;
+    check_cards(community, "community")?;
+    solve_equity(
+        &ShortDeck(rules),
+        hero,
+        villains,
+        community,
+        max_iterations,
+        seed,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::evaluator::eval;
+    use crate::poker::Hand;
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    fn sd(s: &str) -> u32 {
+        eval_short_deck(mask(s), ShortDeckRules::TripsBeatStraight)
+    }
+
+    #[test]
+    fn ace_six_straight() {
+        let wheel = sd("As6c7d8h9d");
+        assert_eq!(category(wheel), 4);
+        assert!(wheel < sd("6c7d8h9dTs"));
+        assert!(wheel > sd("AsAc6d7h8d"));
+        assert_eq!(category(eval(mask("As6c7d8h9d"))), 0);
+    }
+
+    #[test]
+    fn flush_beats_full_house() {
+        let flush = sd("As9s7s8sJsKdQc");
+        let full = sd("AsAcAdKhKsQdJc");
+        assert_eq!(category(flush), 5);
+        assert_eq!(category(full), 6);
+        assert!(flush > full);
+        let both = sd("AhKhQhJh9hAsAcKd");
+        assert_eq!(category(both), 5);
+    }
+
+    #[test]
+    fn trips_vs_straight_rules() {
+        let trips = mask("6s6c6dAhKs");
+        let straight = mask("9sTcJdQhKs");
+        let tbs = ShortDeckRules::TripsBeatStraight;
+        let std = ShortDeckRules::Standard;
+        assert!(eval_short_deck(trips, tbs) > eval_short_deck(straight, tbs));
+        assert!(eval_short_deck(trips, std) < eval_short_deck(straight, std));
+    }
+
+    #[test]
+    fn trips_with_a_straight_play_as_trips() {
+        let board = "6c6d7h8s9d";
+        let both = sd(&format!("6sTs{}", board));
+        assert_eq!(category(both), 3);
+        assert!(both > sd(&format!("TcJc{}", board)));
+        let std = ShortDeckRules::Standard;
+        let both = eval_short_deck(mask(&format!("6sTs{}", board)), std);
+        assert_eq!(category(both), 4);
+        let r = solve_short_deck(
+            mask("6sTs"),
+            &[Range::from_short_deck_notation("TcJc").unwrap()],
+            mask(board),
+            0,
+            1,
+            ShortDeckRules::TripsBeatStraight,
+        )
+        .unwrap();
+        assert_eq!(r.hero_win, 1.0);
+    }
+
+    #[test]
+    fn range_restricted() {
+        assert_eq!(Range::from_short_deck_notation("66+").unwrap().len(), 54);
+        assert!(Range::from_short_deck_notation("55").is_err());
+        assert!(Range::from_short_deck_notation("A2s+").is_err());
+        assert_eq!(Range::any_short_deck().len(), 630);
+        assert_eq!(Deck::short_deck(1).len(), 36);
+    }
+
+    #[test]
+    fn equity_uses_short_deck() {
+        let hero = mask("AsKs");
+        let v = Range::from_short_deck_notation("QhQd").unwrap();
+        let board = mask("6s7s8d");
+        let r = solve_short_deck(hero, &[v], board, 0, 1, ShortDeckRules::default()).unwrap();
+        assert_eq!(r.iterations, 29 * 28 / 2);
+        assert!((r.hero_win + r.hero_tie + r.hero_lose - 1.0).abs() < 1e-9);
+        assert!(
+            solve_short_deck(mask("As2s"), &[], board, 0, 1, ShortDeckRules::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn preflop_but_not_partial_flop() {
+        let hero = mask("AsKs");
+        let v = Range::from_short_deck_notation("QhQd").unwrap();
+        let rules = ShortDeckRules::default();
+        let r = solve_short_deck(hero, std::slice::from_ref(&v), 0, 0, 1, rules).unwrap();
+        assert_eq!(r.iterations, 32 * 31 * 30 * 29 * 28 / 120);
+        assert!(r.hero_win > 0.4 && r.hero_win < 0.6);
+        assert!(solve_short_deck(hero, &[v], mask("6s7s"), 0, 1, rules).is_err());
+    }
+
+    #[test]
+    fn wheel_wins_at_showdown() {
+        // A-6-7-8-9 beats the trips and the overpair.
+        let hero = mask("AsQd");
+        let v = Range::from_short_deck_notation("KhKd").unwrap();
+        let board = mask("6c7d8h9sKc");
+        let r = solve_short_deck(hero, &[v], board, 0, 1, ShortDeckRules::Standard).unwrap();
+        assert_eq!(r.hero_win, 1.0);
+        let board = mask("6c7d8h9sQc");
+        let hero = mask("AsJd");
+        let v = Range::from_short_deck_notation("KhKd").unwrap();
+        let r = solve_short_deck(hero, &[v], board, 0, 1, ShortDeckRules::Standard).unwrap();
+        assert_eq!(r.hero_win, 1.0);
+    }
+}
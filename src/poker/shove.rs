@@ -0,0 +1,278 @@
+use crate::poker::game::{sample_equity, solve_equity};
+use crate::poker::grid::{class_combos, GRID_SIZE};
+use crate::poker::rules::Holdem;
+use crate::poker::Range;
+
+const COMBOS: f64 = 1326.0;
+
+/// Starting hand classes as grid (row, col), strongest first by equity
+/// against a random hand on `community` (empty preflop), from `samples`
+/// deals per combo. Preflop one combo stands for its suit isomorphic class;
+/// on a board a class averages its live combos and classes the board blocks
+/// entirely come last.
+pub fn class_ranking(
+    community: u64,
+    samples: u64,
+    seed: u64,
+) -> Result<Vec<(usize, usize)>, String> {
+    let any = [Range::any()];
+    let mut scored = Vec::with_capacity(GRID_SIZE * GRID_SIZE);
+    for row in 0..GRID_SIZE {
+        for col in 0..GRID_SIZE {
+            let live: Vec<u64> = class_combos(row, col)
+                .iter()
+                .map(|c| c.mask)
+                .filter(|m| m & community == 0)
+                .collect();
+            let heroes = if community == 0 {
+                &live[..1]
+            } else {
+                &live[..]
+            };
+            let mut total = 0.0;
+            for (i, &hero) in heroes.iter().enumerate() {
+                let seed = seed ^ ((row * GRID_SIZE + col) * 16 + i) as u64;
+                let r = sample_equity(&Holdem, hero, &any, community, samples, seed)?;
+                total += 1.0 - r.villain_equity[0];
+            }
+            let equity = if heroes.is_empty() {
+                -1.0
+            } else {
+                total / heroes.len() as f64
+            };
+            scored.push((equity, (row, col)));
+        }
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(scored.into_iter().map(|(_, class)| class).collect())
+}
+
+/// The strongest classes of `ranking` until at least `fraction` of all
+/// 1326 combos are in.
+pub fn top_range(ranking: &[(usize, usize)], fraction: f64) -> Range {
+    let mut combos = Vec::new();
+    for &(row, col) in ranking {
+        if combos.len() as f64 >= fraction * COMBOS {
+            break;
+        }
+        combos.extend(class_combos(row, col));
+    }
+    combos.sort_by_key(|c| c.mask);
+    Range { combos }
+}
+
+/// Hero facing an all-in: `pot` holds everything bet so far, the shove
+/// included, and `to_call` is what hero must add.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShoveSpot {
+    pub hero: u64,
+    /// 0, 3, 4 or 5 board cards.
+    pub community: u64,
+    pub pot: f64,
+    pub to_call: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShoveDecision {
+    /// Hero's share of the final pot against the villain's range.
+    pub equity: f64,
+    /// Equity the pot odds ask for: `to_call / (pot + to_call)`.
+    pub required: f64,
+    /// Chips calling wins on average compared to folding.
+    pub call_ev: f64,
+    /// Narrowest top-of-ranking villain range, as a fraction of all combos,
+    /// that hero can call profitably; `None` when even any two cards are
+    /// too strong.
+    pub break_even_width: Option<f64>,
+}
+
+impl ShoveDecision {
+    pub fn should_call(&self) -> bool {
+        self.call_ev > 0.0
+    }
+}
+
+impl ShoveSpot {
+    /// Hero's equity against `villain`, which needs a live combo.
+    fn equity(&self, villain: &Range, max_iterations: u64, seed: u64) -> Result<f64, String> {
+        let r = solve_equity(
+            &Holdem,
+            self.hero,
+            std::slice::from_ref(villain),
+            self.community,
+            max_iterations,
+            seed,
+        )?;
+        Ok(1.0 - r.villain_equity[0])
+    }
+
+    /// Call or fold against `villain`, with the break-even width found by
+    /// bisection over prefixes of `ranking`, which should come from
+    /// `class_ranking` on the same community cards.
+    pub fn decide(
+        &self,
+        villain: &Range,
+        ranking: &[(usize, usize)],
+        max_iterations: u64,
+        seed: u64,
+    ) -> Result<ShoveDecision, String> {
+        if self.hero.count_ones() != 2 {
+            return Err("hero must have exactly 2 cards".into());
+        }
+        if ![0, 3, 4, 5].contains(&self.community.count_ones()) {
+            return Err("community must have 0 or 3-5 cards".into());
+        }
+        if self.to_call <= 0.0 || self.pot < 0.0 {
+            return Err("amount to call must be positive and pot non-negative".into());
+        }
+        let equity = self.equity(villain, max_iterations, seed)?;
+        let required = self.to_call / (self.pot + self.to_call);
+
+        let prefix = |k: usize| {
+            let mut combos: Vec<_> = ranking[..k]
+                .iter()
+                .flat_map(|&(row, col)| class_combos(row, col))
+                .collect();
+            combos.sort_by_key(|c| c.mask);
+            Range { combos }
+        };
+        // a prefix the dead cards block entirely never gets the price
+        let profitable = |k: usize| -> Result<bool, String> {
+            let range = prefix(k);
+            if range
+                .live_combos(self.hero | self.community)
+                .next()
+                .is_none()
+            {
+                return Ok(false);
+            }
+            Ok(self.equity(&range, max_iterations, seed)? >= required)
+        };
+        let break_even_width = if ranking.is_empty() || !profitable(ranking.len())? {
+            None
+        } else {
+            let (mut lo, mut hi) = (0, ranking.len());
+            while hi - lo > 1 {
+                let mid = (lo + hi) / 2;
+                if profitable(mid)? {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            Some(prefix(hi).len() as f64 / COMBOS)
+        };
+        Ok(ShoveDecision {
+            equity,
+            required,
+            call_ev: equity * (self.pot + self.to_call) - self.to_call,
+            break_even_width,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::grid::class_label;
+    use crate::poker::Hand;
+    use std::sync::OnceLock;
+
+    fn ranking() -> &'static [(usize, usize)] {
+        static RANKING: OnceLock<Vec<(usize, usize)>> = OnceLock::new();
+        RANKING.get_or_init(|| class_ranking(0, 400, 1).unwrap())
+    }
+
+    fn spot(hero: &str, pot: f64, to_call: f64) -> ShoveSpot {
+        ShoveSpot {
+            hero: Hand::from_string(hero).mask,
+            community: 0,
+            pot,
+            to_call,
+        }
+    }
+
+    #[test]
+    fn ranking_orders_classes() {
+        let r = ranking();
+        assert_eq!(r.len(), 169);
+        assert_eq!(class_label(r[0].0, r[0].1), "AA");
+        let last: Vec<String> = r[166..].iter().map(|&(a, b)| class_label(a, b)).collect();
+        assert!(last.iter().any(|l| l == "32o" || l == "42o"), "{:?}", last);
+    }
+
+    #[test]
+    fn top_ranges_grow() {
+        assert_eq!(top_range(ranking(), 1.0).len(), 1326);
+        assert!(top_range(ranking(), 0.0).is_empty());
+        let top = top_range(ranking(), 0.05);
+        assert!(top.len() >= 66 && top.len() < 90, "{} combos", top.len());
+    }
+
+    #[test]
+    fn pot_odds_and_call_ev() {
+        let villain = Range::from_notation("KK").unwrap();
+        let d = spot("AhAd", 100.0, 50.0)
+            .decide(&villain, ranking(), 20_000, 1)
+            .unwrap();
+        assert!((d.required - 1.0 / 3.0).abs() < 1e-12);
+        assert!((d.equity - 0.82).abs() < 0.02, "equity {}", d.equity);
+        assert!((d.call_ev - (d.equity * 150.0 - 50.0)).abs() < 1e-9);
+        assert!(d.should_call());
+        // aces beat any range that holds a pair or better often enough
+        let width = d.break_even_width.unwrap();
+        assert!(width < 0.01, "width {}", width);
+    }
+
+    #[test]
+    fn weak_hands_need_wide_ranges() {
+        let villain = Range::from_notation("22+,A2+").unwrap();
+        let pot_odds = spot("Th9h", 150.0, 100.0)
+            .decide(&villain, ranking(), 5_000, 1)
+            .unwrap();
+        let width = pot_odds.break_even_width.unwrap();
+        assert!(width > 0.15 && width < 0.5, "width {}", width);
+        // trash against a huge overbet never gets the price
+        let d = spot("7c2d", 10.0, 100.0)
+            .decide(&villain, ranking(), 5_000, 1)
+            .unwrap();
+        assert_eq!(d.break_even_width, None);
+        assert!(!d.should_call());
+    }
+
+    #[test]
+    fn rejects_bad_spots() {
+        let villain = Range::from_notation("KK").unwrap();
+        assert!(spot("AhAd", 100.0, 0.0)
+            .decide(&villain, ranking(), 100, 1)
+            .is_err());
+        let mut s = spot("AhAd", 100.0, 50.0);
+        s.community = Hand::from_string("2c3c").mask;
+        assert!(s.decide(&villain, ranking(), 100, 1).is_err());
+        // no KK combo is left once hero and the board hold three kings
+        let s = spot("KhKd", 100.0, 50.0);
+        let blocked = ShoveSpot {
+            community: Hand::from_string("Ks7c2d").mask,
+            ..s
+        };
+        let err = blocked.decide(&villain, ranking(), 100, 1).unwrap_err();
+        assert_eq!(err, "villain range has no live combos");
+    }
+
+    #[test]
+    fn postflop_ranking_uses_the_board() {
+        let board = Hand::from_string("7c7d2h").mask;
+        let r = class_ranking(board, 60, 1).unwrap();
+        let labels: Vec<String> = r.iter().map(|&(a, b)| class_label(a, b)).collect();
+        let rank = |l: &str| labels.iter().position(|x| x == l).unwrap();
+        assert!(rank("72o") < rank("AA"), "{:?}", &labels[..5]);
+        assert!(rank("77") < rank("72o"));
+        let s = ShoveSpot {
+            community: board,
+            ..spot("AhKh", 100.0, 50.0)
+        };
+        let villain = Range::from_notation("AA,KK,77,72").unwrap();
+        let d = s.decide(&villain, &r, 2_000, 1).unwrap();
+        assert!(d.break_even_width.unwrap() > 0.2);
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::poker::card::Card;
+use crate::poker::cfr::CfrSolver;
+use crate::poker::evaluator::pack_suit;
+use crate::poker::grid::{locate, RangeGrid, GRID_SIZE};
+use crate::poker::hand::SUIT_COUNT;
+use crate::poker::isomorphism::unpack_suit;
+use crate::poker::range::Combo;
+use crate::poker::tree::{Action, ActionTree, NodeKind, TreeConfig};
+use crate::poker::{Hand, Range};
+
+const MAGIC: &[u8; 4] = b"PSSF";
+const VERSION: u8 = 1;
+/// Frequencies are stored as multiples of `1 / FREQUENCY_SCALE`.
+const FREQUENCY_SCALE: f64 = u16::MAX as f64;
+
+/// Strategy and expected values at one stored board of an action node.
+#[derive(Debug, Clone, PartialEq)]
+struct Spot {
+    board: u64,
+    /// One row of combos per action.
+    frequencies: Vec<u16>,
+    values: Vec<f32>,
+}
+
+/// A solved subgame as saved to disk: the betting tree, both ranges and
+/// the acting player's strategy and expected values at every action node,
+/// once per suit canonical board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyFile {
+    pub board: u64,
+    pub tree: ActionTree,
+    /// Both players' live combos, out of position first. Per combo data
+    /// follows this order.
+    pub ranges: [Range; 2],
+    pub iterations: u32,
+    pub exploitability: f64,
+    spots: Vec<Vec<Spot>>,
+}
+
+/// Strategy of the acting player at one node once its board is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStrategy {
+    pub player: usize,
+    pub board: u64,
+    pub actions: Vec<Action>,
+    /// Actions as `ActionTree::label` names them.
+    pub labels: Vec<String>,
+    /// Combos of the player that are live on `board`.
+    pub combos: Vec<Combo>,
+    /// One row per action, one frequency per combo.
+    pub frequencies: Vec<Vec<f64>>,
+    /// Chips each combo expects to win from the pot.
+    pub values: Vec<f64>,
+}
+
+impl NodeStrategy {
+    /// Share of the range, by weight, taking `action`.
+    pub fn frequency(&self, action: usize) -> f64 {
+        let total: f64 = self.combos.iter().map(|c| c.weight as f64).sum();
+        let taken: f64 = self
+            .combos
+            .iter()
+            .zip(&self.frequencies[action])
+            .map(|(c, f)| c.weight as f64 * f)
+            .sum();
+        taken / total
+    }
+
+    /// Expected chips of the whole range.
+    pub fn value(&self) -> f64 {
+        let total: f64 = self.combos.iter().map(|c| c.weight as f64).sum();
+        let value: f64 = self
+            .combos
+            .iter()
+            .zip(&self.values)
+            .map(|(c, v)| c.weight as f64 * v)
+            .sum();
+        value / total
+    }
+
+    /// How often each hand class takes `action`, averaged over its combos
+    /// in range; classes out of range show 0.
+    pub fn grid(&self, action: usize) -> RangeGrid {
+        let mut taken = [[0.0f32; GRID_SIZE]; GRID_SIZE];
+        let mut total = [[0.0f32; GRID_SIZE]; GRID_SIZE];
+        for (c, &f) in self.combos.iter().zip(&self.frequencies[action]) {
+            if let Some((row, col, _)) = locate(c.mask) {
+                taken[row][col] += c.weight * f as f32;
+                total[row][col] += c.weight;
+            }
+        }
+        for (t, w) in taken.iter_mut().flatten().zip(total.iter().flatten()) {
+            if *w > 0.0 {
+                *t /= w;
+            }
+        }
+        RangeGrid::from_frequencies(&taken)
+    }
+}
+
+/// The 24 orderings of the suits, identity first.
+fn suit_permutations() -> Vec<[u32; 4]> {
+    let mut out = Vec::with_capacity(24);
+    for a in 0..SUIT_COUNT {
+        for b in (0..SUIT_COUNT).filter(|&b| b != a) {
+            for c in (0..SUIT_COUNT).filter(|&c| c != a && c != b) {
+                out.push([a, b, c, 6 - a - b - c]);
+            }
+        }
+    }
+    out
+}
+
+fn permute(mask: u64, perm: &[u32; 4]) -> u64 {
+    (0..SUIT_COUNT).fold(0, |out, s| {
+        out | unpack_suit(pack_suit(mask, s), perm[s as usize])
+    })
+}
+
+/// Little endian reads that fail on a short file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < n {
+            return Err("truncated strategy file".into());
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+impl StrategyFile {
+    /// Snapshot of the solver's average strategy.
+    pub fn from_solver(solver: &CfrSolver) -> Self {
+        let spots = solver
+            .spots()
+            .into_iter()
+            .map(|node| {
+                node.into_iter()
+                    .map(|(board, frequencies, values)| Spot {
+                        board,
+                        frequencies: frequencies
+                            .iter()
+                            .map(|f| (f * FREQUENCY_SCALE).round() as u16)
+                            .collect(),
+                        values: values.iter().map(|&v| v as f32).collect(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            board: solver.board,
+            tree: solver.tree.clone(),
+            ranges: [0, 1].map(|p| Range {
+                combos: solver.combos(p).to_vec(),
+            }),
+            iterations: solver.iterations,
+            exploitability: solver.exploitability(),
+            spots,
+        }
+    }
+
+    /// Little endian binary layout: magic, version, board, iterations,
+    /// exploitability, the tree config as text behind its length, each
+    /// range as a combo count then two card indices and a weight per
+    /// combo, then for every action node in tree order its board count and
+    /// per board the board, a 16 bit frequency per action and combo and an
+    /// expected value per combo.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.board.to_le_bytes());
+        out.extend_from_slice(&self.iterations.to_le_bytes());
+        out.extend_from_slice(&self.exploitability.to_le_bytes());
+        let config = self.tree.config.to_string();
+        out.extend_from_slice(&(config.len() as u32).to_le_bytes());
+        out.extend_from_slice(config.as_bytes());
+        for range in &self.ranges {
+            out.extend_from_slice(&(range.combos.len() as u32).to_le_bytes());
+            for c in &range.combos {
+                out.push(c.mask.trailing_zeros() as u8);
+                out.push(63 - c.mask.leading_zeros() as u8);
+                out.extend_from_slice(&c.weight.to_le_bytes());
+            }
+        }
+        for (node, _) in self
+            .spots
+            .iter()
+            .zip(&self.tree.nodes)
+            .filter(|(_, n)| matches!(n.kind, NodeKind::Action { .. }))
+        {
+            out.extend_from_slice(&(node.len() as u32).to_le_bytes());
+            for spot in node {
+                out.extend_from_slice(&spot.board.to_le_bytes());
+                for f in &spot.frequencies {
+                    out.extend_from_slice(&f.to_le_bytes());
+                }
+                for v in &spot.values {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 5 || &bytes[..4] != MAGIC {
+            return Err("not a strategy file".into());
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("unsupported strategy file version {}", bytes[4]));
+        }
+        let mut r = Reader { bytes: &bytes[5..] };
+        let board = r.u64()?;
+        let iterations = r.u32()?;
+        let exploitability = r.f64()?;
+        let len = r.u32()? as usize;
+        let config = std::str::from_utf8(r.take(len)?)
+            .map_err(|_| "tree config is not text".to_string())?
+            .parse::<TreeConfig>()?;
+        let tree = ActionTree::new(config)?;
+        let mut ranges = [Range::default(), Range::default()];
+        for range in ranges.iter_mut() {
+            let count = r.u32()? as usize;
+            for _ in 0..count {
+                let cards = r.take(2)?;
+                if cards[0] >= cards[1] || cards[1] >= 52 {
+                    return Err("bad combo in strategy file".into());
+                }
+                let mask = 1u64 << cards[0] | 1u64 << cards[1];
+                if mask & board != 0 {
+                    return Err("combo blocked by the board".into());
+                }
+                range.combos.push(Combo::with_weight(mask, r.f32()?));
+            }
+        }
+        let mut spots = Vec::with_capacity(tree.len());
+        for node in &tree.nodes {
+            let NodeKind::Action {
+                player, actions, ..
+            } = &node.kind
+            else {
+                spots.push(Vec::new());
+                continue;
+            };
+            let n = ranges[*player].combos.len();
+            let count = r.u32()?;
+            let mut stored = Vec::new();
+            for _ in 0..count {
+                let board = r.u64()?;
+                let frequencies = (0..actions.len() * n)
+                    .map(|_| r.u16())
+                    .collect::<Result<_, _>>()?;
+                let values = (0..n).map(|_| r.f32()).collect::<Result<_, _>>()?;
+                stored.push(Spot {
+                    board,
+                    frequencies,
+                    values,
+                });
+            }
+            spots.push(stored);
+        }
+        if !r.bytes.is_empty() {
+            return Err("trailing bytes after strategy file".into());
+        }
+        Ok(Self {
+            board,
+            tree,
+            ranges,
+            iterations,
+            exploitability,
+            spots,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes())
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Follow a path such as "check > bet 75% > call > Ah" from the root,
+    /// naming actions by their label or their `Display` form and chance
+    /// nodes by the card dealt. Returns the node reached and the cards.
+    pub fn navigate(&self, path: &str) -> Result<(usize, Vec<Card>), String> {
+        let (mut node, mut dealt, mut board) = (0, Vec::new(), self.board);
+        for step in path.split('>').map(str::trim).filter(|s| !s.is_empty()) {
+            match &self.tree.nodes[node].kind {
+                NodeKind::Action {
+                    actions, children, ..
+                } => {
+                    let labels: Vec<String> = (0..actions.len())
+                        .filter_map(|a| self.tree.label(node, a))
+                        .collect();
+                    let a = (0..actions.len())
+                        .find(|&a| {
+                            labels[a].eq_ignore_ascii_case(step)
+                                || actions[a].to_string().eq_ignore_ascii_case(step)
+                        })
+                        .ok_or_else(|| format!("no {:?} here, try {}", step, labels.join(", ")))?;
+                    node = children[a];
+                }
+                &NodeKind::Chance { child } => {
+                    let card: Card = step.parse()?;
+                    if board & card.mask() != 0 {
+                        return Err(format!("{} is already out", card));
+                    }
+                    board |= card.mask();
+                    dealt.push(card);
+                    node = child;
+                }
+                _ => return Err(format!("the hand is over before {:?}", step)),
+            }
+        }
+        Ok((node, dealt))
+    }
+
+    /// Strategy at action node `node` once `dealt` (one card per street
+    /// before the node's) came. Boards saved under a suit isomorphic name
+    /// are found by relabelling suits.
+    pub fn strategy(&self, node: usize, dealt: &[Card]) -> Result<NodeStrategy, String> {
+        let Some(NodeKind::Action {
+            player, actions, ..
+        }) = self.tree.nodes.get(node).map(|n| &n.kind)
+        else {
+            return Err(format!("node {} is not an action node", node));
+        };
+        let street = self.tree.nodes[node].street;
+        if dealt.len() != street {
+            return Err(format!("node {} needs {} dealt cards", node, street));
+        }
+        let mut board = self.board;
+        for c in dealt {
+            if board & c.mask() != 0 {
+                return Err(format!("{} is already out", c));
+            }
+            board |= c.mask();
+        }
+        let index: Vec<HashMap<u64, usize>> = self
+            .ranges
+            .iter()
+            .map(|r| {
+                r.combos
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (c.mask, i))
+                    .collect()
+            })
+            .collect();
+        // each combo's position in the stored board's suits
+        let relabel = |perm: &[u32; 4], p: usize| -> Option<Vec<usize>> {
+            self.ranges[p]
+                .combos
+                .iter()
+                .map(|c| {
+                    index[p]
+                        .get(&permute(c.mask, perm))
+                        .copied()
+                        .filter(|&j| self.ranges[p].combos[j].weight == c.weight)
+                })
+                .collect()
+        };
+        let found = suit_permutations().iter().find_map(|perm| {
+            let target = permute(board, perm);
+            let spot = self.spots[node].iter().find(|s| s.board == target)?;
+            let stored = relabel(perm, *player)?;
+            relabel(perm, 1 - player)?;
+            Some((spot, stored))
+        });
+        let Some((spot, stored)) = found else {
+            return Err(format!("board {} is not in the file", Hand { mask: board }));
+        };
+        let n = self.ranges[*player].combos.len();
+        let live: Vec<usize> = (0..n)
+            .filter(|&h| self.ranges[*player].combos[h].mask & board == 0)
+            .collect();
+        Ok(NodeStrategy {
+            player: *player,
+            board,
+            actions: actions.clone(),
+            labels: (0..actions.len())
+                .filter_map(|a| self.tree.label(node, a))
+                .collect(),
+            combos: live
+                .iter()
+                .map(|&h| self.ranges[*player].combos[h])
+                .collect(),
+            frequencies: (0..actions.len())
+                .map(|a| {
+                    live.iter()
+                        .map(|&h| spot.frequencies[a * n + stored[h]] as f64 / FREQUENCY_SCALE)
+                        .collect()
+                })
+                .collect(),
+            values: live
+                .iter()
+                .map(|&h| spot.values[stored[h]] as f64)
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::tree::{StreetSizes, OOP};
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    fn card(s: &str) -> Card {
+        s.parse().unwrap()
+    }
+
+    /// Turn subgame on a monotone board where hearts, diamonds and clubs
+    /// are interchangeable.
+    fn turn_file() -> (CfrSolver, StrategyFile) {
+        let config = TreeConfig {
+            stack: 1000.0,
+            streets: vec![StreetSizes::new(&[0.5], &[]), StreetSizes::new(&[1.0], &[])],
+            max_raises: 1,
+            all_in: false,
+            ..TreeConfig::default()
+        };
+        let oop = Range::from_notation("AA,JJ").unwrap();
+        let ip = Range::from_notation("QQ").unwrap();
+        let mut solver = CfrSolver::new(mask("Ks9s6s3s"), [&oop, &ip], config).unwrap();
+        solver.solve(100);
+        let file = StrategyFile::from_solver(&solver);
+        (solver, file)
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (_, file) = turn_file();
+        let bytes = file.to_bytes();
+        assert_eq!(StrategyFile::from_bytes(&bytes).unwrap(), file);
+        let path = std::env::temp_dir().join("poker-solver-strategy-test.pssf");
+        file.save(&path).unwrap();
+        assert_eq!(StrategyFile::load(&path).unwrap(), file);
+        let _ = fs::remove_file(&path);
+        assert!(StrategyFile::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(StrategyFile::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(StrategyFile::from_bytes(b"nope").is_err());
+        let mut future = bytes.clone();
+        future[4] = VERSION + 1;
+        assert!(StrategyFile::from_bytes(&future)
+            .unwrap_err()
+            .contains("version"));
+    }
+
+    #[test]
+    fn reader_matches_solver_on_every_river() {
+        let (solver, file) = turn_file();
+        let (river, dealt) = file.navigate("check > check > 2h").unwrap();
+        assert_eq!(dealt, [card("2h")]);
+        for c in ["2h", "2d", "2c", "As", "Qd"] {
+            let dealt = [card(c)];
+            let read = file.strategy(river, &dealt).unwrap();
+            let solved = solver.strategy(river, &dealt).unwrap();
+            let board = file.board | card(c).mask();
+            let live: Vec<usize> = (0..solver.combos(OOP).len())
+                .filter(|&h| solver.combos(OOP)[h].mask & board == 0)
+                .collect();
+            assert_eq!(read.combos.len(), live.len());
+            for (row, solved) in read.frequencies.iter().zip(&solved) {
+                for (f, &h) in row.iter().zip(&live) {
+                    assert!((f - solved[h]).abs() < 1e-4, "{} on {}", f, c);
+                }
+            }
+        }
+        assert!(file.strategy(river, &[]).is_err());
+        assert!(file.strategy(river, &[card("Ks")]).is_err());
+    }
+
+    #[test]
+    fn navigates_by_label() {
+        let (_, file) = turn_file();
+        let (node, dealt) = file.navigate("bet 50% > call > Ah > check").unwrap();
+        assert_eq!(dealt, [card("Ah")]);
+        let s = file.strategy(node, &dealt).unwrap();
+        assert_eq!(s.player, 1);
+        assert_eq!(s.labels, ["check", "bet 100%"]);
+        assert_eq!(
+            file.navigate("check > bet 50").unwrap(),
+            file.navigate("Check > BET 50%").unwrap()
+        );
+        assert_eq!(file.navigate("").unwrap(), (0, Vec::new()));
+        assert!(file.navigate("bet 75%").unwrap_err().contains("bet 50%"));
+        assert!(file.navigate("bet 50% > call > Ks").is_err());
+        assert!(file.navigate("bet 50% > fold > check").is_err());
+    }
+
+    #[test]
+    fn grids_show_class_frequencies() {
+        let (_, file) = turn_file();
+        let root = file.strategy(0, &[]).unwrap();
+        let total: f64 = (0..root.actions.len()).map(|a| root.frequency(a)).sum();
+        assert!((total - 1.0).abs() < 1e-3);
+        // only pairs are in range
+        assert_eq!(root.grid(0).frequency(0, 1), 0.0);
+        let check = root.grid(0).frequency(0, 0) + root.grid(1).frequency(0, 0);
+        assert!((check - 1.0).abs() < 1e-3);
+        // aces beat queens at showdown, jacks do not
+        let aces = root
+            .combos
+            .iter()
+            .position(|c| c.mask == mask("AhAd"))
+            .unwrap();
+        let jacks = root
+            .combos
+            .iter()
+            .position(|c| c.mask == mask("JhJd"))
+            .unwrap();
+        assert!(root.values[aces] > root.values[jacks]);
+        assert!(root.value() > 0.0 && root.value() < file.tree.config.pot * 2.0);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::poker::deck::XorShift64;
+use crate::poker::game::{
+    binom, finalize, pick_random_board, tally_outcome, EquityResult, DEFAULT_MC_ITERATIONS,
+    EXACT_WORK_LIMIT,
+};
+use crate::poker::omaha::for_each_subset;
+use crate::poker::rules::GameRules;
+use crate::poker::Range;
+
+/// Cards per player at showdown.
+pub const STUD_CARDS: u32 = 7;
+
+/// One seat in a stud hand.
+#[derive(Debug, Clone, Default)]
+pub struct StudPlayer {
+    /// Cards we know: every card for hero, the upcards for a villain.
+    pub known: u64,
+    /// Face down cards already dealt that we cannot see.
+    pub hidden: u32,
+    /// Optional read on the hidden cards; combos must have `hidden` cards.
+    /// Without one the hidden cards are random.
+    pub range: Option<Range>,
+}
+
+impl StudPlayer {
+    pub fn hero(cards: u64) -> Self {
+        Self {
+            known: cards,
+            hidden: 0,
+            range: None,
+        }
+    }
+
+    pub fn villain(upcards: u64, hidden: u32) -> Self {
+        Self {
+            known: upcards,
+            hidden,
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: Range) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Cards still to come on later streets.
+    fn to_come(&self) -> u32 {
+        STUD_CARDS - self.known.count_ones() - self.hidden
+    }
+}
+
+/// Seven-card stud equity for `players[0]` against the other seats. Cards
+/// in `dead` (folded upcards and the like) cannot be dealt. Showdowns are
+/// scored by `rules` on each player's seven cards with an empty board, so
+/// `Holdem` gives stud high, `HiLo(HiLoGame::Holdem)` stud-8 and
+/// `Lowball(LowballRules::AceToFive)` razz. Remaining streets are enumerated
+/// when cheap and sampled otherwise.
+pub fn solve_stud<R: GameRules + ?Sized>(
+    rules: &R,
+    players: &[StudPlayer],
+    dead: u64,
+    max_iterations: u64,
+    seed: u64,
+) -> Result<EquityResult, String> {
+    if players.len() < 2 {
+        return Err("need at least two players".into());
+    }
+    let mut used = dead | !rules.deck();
+    let mut unknown = 0;
+    for (i, p) in players.iter().enumerate() {
+        if p.known & used != 0 {
+            return Err(format!("player {} holds a dead or repeated card", i));
+        }
+        if p.known.count_ones() + p.hidden > STUD_CARDS {
+            return Err(format!("player {} has more than 7 cards", i));
+        }
+        if let Some(r) = &p.range {
+            if r.combos.iter().any(|c| c.mask.count_ones() != p.hidden) {
+                return Err(format!(
+                    "player {} range must have {} card combos",
+                    i, p.hidden
+                ));
+            }
+        }
+        used |= p.known;
+        unknown += p.hidden + p.to_come();
+    }
+    let free = 64 - used.count_ones();
+    if unknown > free {
+        return Err("not enough cards left to deal".into());
+    }
+
+    let mut work: u128 = rules.cost(STUD_CARDS);
+    let mut left = free;
+    for p in players {
+        let choices = match &p.range {
+            Some(r) => {
+                let live = r.live_combos(used).filter(|c| c.weight > 0.0).count() as u128;
+                live * binom(left - p.hidden, p.to_come())
+            }
+            None => binom(left, p.hidden + p.to_come()),
+        };
+        if choices == 0 {
+            return Err("a player's range has no live combos".into());
+        }
+        work = work.saturating_mul(choices);
+        left -= p.hidden + p.to_come();
+    }
+
+    let n = players.len();
+    let mut acc = vec![0.0f64; n];
+    let (mut win, mut tie, mut lose) = (0.0, 0.0, 0.0);
+    let mut iters = 0u64;
+    let mut total = 0.0;
+    let mut shares = vec![0.0f64; n];
+    let mut visit = |hands: &[u64], weight: f64| {
+        rules.split(hands, 0, &mut shares);
+        tally_outcome(&shares, &mut acc, &mut win, &mut tie, &mut lose, weight);
+        iters += 1;
+        total += weight;
+    };
+    let mut hands = vec![0u64; n];
+    if work <= EXACT_WORK_LIMIT {
+        let deck = rules.deck();
+        enumerate_players(0, used, 1.0, deck, players, &mut hands, &mut visit);
+    } else {
+        let target = if max_iterations == 0 {
+            DEFAULT_MC_ITERATIONS
+        } else {
+            max_iterations
+        };
+        sample_players(used, players, target, seed, &mut hands, &mut visit)?;
+    }
+    finalize(iters, total, acc, win, tie, lose)
+}
+
+fn enumerate_players<F: FnMut(&[u64], f64)>(
+    i: usize,
+    used: u64,
+    weight: f64,
+    deck: u64,
+    players: &[StudPlayer],
+    hands: &mut [u64],
+    f: &mut F,
+) {
+    if i == players.len() {
+        f(hands, weight);
+        return;
+    }
+    let p = &players[i];
+    let free = deck & !used;
+    match &p.range {
+        Some(r) => {
+            let combos: Vec<(u64, f64)> = r
+                .live_combos(used)
+                .filter(|c| c.weight > 0.0)
+                .map(|c| (c.mask, c.weight as f64))
+                .collect();
+            for (m, w) in combos {
+                for_each_subset(free & !m, p.to_come(), &mut |rest| {
+                    hands[i] = p.known | m | rest;
+                    let next = used | m | rest;
+                    enumerate_players(i + 1, next, weight * w, deck, players, hands, f);
+                });
+            }
+        }
+        None => {
+            for_each_subset(free, p.hidden + p.to_come(), &mut |rest| {
+                hands[i] = p.known | rest;
+                enumerate_players(i + 1, used | rest, weight, deck, players, hands, f);
+            });
+        }
+    }
+}
+
+/// Samples every range player's hidden cards first, rejecting collisions so
+/// combos stay weighted correctly, then deals the random cards.
+fn sample_players<F: FnMut(&[u64], f64)>(
+    dead: u64,
+    players: &[StudPlayer],
+    target: u64,
+    seed: u64,
+    hands: &mut [u64],
+    f: &mut F,
+) -> Result<(), String> {
+    let live: Vec<Vec<(u64, f64)>> = players
+        .iter()
+        .map(|p| match &p.range {
+            Some(r) => r
+                .live_combos(dead)
+                .filter(|c| c.weight > 0.0)
+                .map(|c| (c.mask, c.weight as f64))
+                .collect(),
+            None => Vec::new(),
+        })
+        .collect();
+    let mut rng = XorShift64::new(seed);
+    let mut iters = 0u64;
+    let mut attempts = 0u64;
+    'sample: while iters < target && attempts < target.saturating_mul(50) {
+        attempts += 1;
+        let mut used = dead;
+        let mut weight = 1.0;
+        for (i, p) in players.iter().enumerate() {
+            hands[i] = p.known;
+            if p.range.is_some() {
+                let (m, w) = live[i][rng.range(live[i].len())];
+                if m & used != 0 {
+                    continue 'sample;
+                }
+                hands[i] |= m;
+                used |= m;
+                weight *= w;
+            }
+        }
+        for (i, p) in players.iter().enumerate() {
+            let random = if p.range.is_some() {
+                p.to_come()
+            } else {
+                p.hidden + p.to_come()
+            };
+            let rest = pick_random_board(used, random, &mut rng);
+            hands[i] |= rest;
+            used |= rest;
+        }
+        f(hands, weight);
+        iters += 1;
+    }
+    if iters == 0 {
+        return Err("Monte Carlo could not draw a valid sample".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::{Hand, HiLo, HiLoGame, Holdem, Lowball, LowballRules};
+
+    fn mask(s: &str) -> u64 {
+        Hand::from_string(s).mask
+    }
+
+    #[test]
+    fn seventh_street_showdown() {
+        let players = [
+            StudPlayer::hero(mask("AsAdKhKc7s8d2c")),
+            StudPlayer::hero(mask("QsQdJhJc7h8c3c")),
+        ];
+        let r = solve_stud(&Holdem, &players, 0, 0, 1).unwrap();
+        assert_eq!(r.iterations, 1);
+        assert_eq!(r.hero_win, 1.0);
+    }
+
+    #[test]
+    fn one_card_to_come_with_dead_cards() {
+        let hero = StudPlayer::hero(mask("2h5h9hKhAs3c"));
+        let villain = StudPlayer::villain(mask("QsJcTd8c"), 2)
+            .with_range(Range::from_notation("QdQc").unwrap());
+        let players = [hero, villain];
+        let r = solve_stud(&Holdem, &players, 0, 0, 1).unwrap();
+        assert_eq!(r.iterations, 40 * 39);
+        let dead = mask("4h6h7h8h");
+        let fewer_outs = solve_stud(&Holdem, &players, dead, 0, 1).unwrap();
+        assert!(fewer_outs.hero_win < r.hero_win);
+        assert!((r.hero_win + r.hero_tie + r.hero_lose - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn razz_and_stud8() {
+        let players = [
+            StudPlayer::hero(mask("As2d3c4h8sKdKc")),
+            StudPlayer::hero(mask("QsQdJhJc7h7c6c")),
+        ];
+        let razz = solve_stud(&Lowball(LowballRules::AceToFive), &players, 0, 0, 1).unwrap();
+        assert_eq!(razz.hero_win, 1.0);
+        let hilo = solve_stud(&HiLo(HiLoGame::Holdem), &players, 0, 0, 1).unwrap();
+        assert_eq!(hilo.villain_equity, vec![0.5]);
+        assert_eq!(hilo.hero_tie, 1.0);
+    }
+
+    #[test]
+    fn third_street_samples() {
+        let players = [
+            StudPlayer::hero(mask("AsAhKd")),
+            StudPlayer::villain(mask("7c"), 2),
+            StudPlayer::villain(mask("9s"), 2),
+        ];
+        let r = solve_stud(&Holdem, &players, mask("2c3d"), 2000, 7).unwrap();
+        assert_eq!(r.iterations, 2000);
+        let sum = r.hero_win + r.hero_tie + r.hero_lose;
+        assert!((sum - 1.0).abs() < 1e-9);
+        assert!(r.hero_win > r.villain_equity[0]);
+    }
+
+    #[test]
+    fn rejects_bad_setup() {
+        let hero = StudPlayer::hero(mask("AsAhKd"));
+        assert!(solve_stud(&Holdem, std::slice::from_ref(&hero), 0, 0, 1).is_err());
+        let clash = StudPlayer::villain(mask("As"), 2);
+        assert!(solve_stud(&Holdem, &[hero.clone(), clash], 0, 0, 1).is_err());
+        let too_many = StudPlayer::villain(mask("7c8c9cTc"), 4);
+        assert!(solve_stud(&Holdem, &[hero.clone(), too_many], 0, 0, 1).is_err());
+        let wrong_size =
+            StudPlayer::villain(mask("7c"), 1).with_range(Range::from_notation("KK").unwrap());
+        assert!(solve_stud(&Holdem, &[hero, wrong_size], 0, 0, 1).is_err());
+    }
+}
@@ -0,0 +1,606 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Player out of position, first to act.
+pub const OOP: usize = 0;
+/// Player in position.
+pub const IP: usize = 1;
+
+/// Sizes offered on one street.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreetSizes {
+    /// Bet sizes as fractions of the pot.
+    pub bet: Vec<f64>,
+    /// Raise sizes as fractions of the pot after calling.
+    pub raise: Vec<f64>,
+    /// Bets plus raises allowed on this street, overriding
+    /// `TreeConfig::max_raises`.
+    pub max_raises: Option<u32>,
+}
+
+impl StreetSizes {
+    pub fn new(bet: &[f64], raise: &[f64]) -> Self {
+        Self {
+            bet: bet.to_vec(),
+            raise: raise.to_vec(),
+            max_raises: None,
+        }
+    }
+}
+
+/// Betting options of a subgame. As text it is one line of `key=value`
+/// pairs, for example
+/// `pot=100 stack=400 raises=3 allin=true threshold=0.67 sizes=33,75/100;75/100/2`.
+/// `sizes` lists the streets separated by `;`, each as bet percentages,
+/// raise percentages and an optional raise cap separated by `/`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeConfig {
+    /// Chips in the pot when the subgame starts.
+    pub pot: f64,
+    /// Effective stack behind, per player.
+    pub stack: f64,
+    /// Sizes per street, starting with the street the subgame begins on;
+    /// its length is the number of streets played.
+    pub streets: Vec<StreetSizes>,
+    /// Bets plus raises allowed per street.
+    pub max_raises: u32,
+    /// Offer all-in besides the sized bets and raises.
+    pub all_in: bool,
+    /// Sized bets and raises committing at least this fraction of the
+    /// stack are made all-in instead.
+    pub all_in_threshold: f64,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            pot: 100.0,
+            stack: 100.0,
+            streets: vec![StreetSizes::new(&[0.33, 0.75], &[1.0])],
+            max_raises: 3,
+            all_in: true,
+            all_in_threshold: 1.0,
+        }
+    }
+}
+
+/// Sizes as percentages, comma separated, rounded to six decimals.
+fn format_sizes(sizes: &[f64]) -> String {
+    sizes
+        .iter()
+        .map(|s| format!("{}", (s * 1e8).round() / 1e6))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_sizes(s: &str) -> Result<Vec<f64>, String> {
+    s.split(',')
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            x.parse::<f64>()
+                .map(|p| p / 100.0)
+                .map_err(|_| format!("bad size {:?}", x))
+        })
+        .collect()
+}
+
+impl fmt::Display for TreeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let streets: Vec<String> = self
+            .streets
+            .iter()
+            .map(|s| {
+                let sizes = format!("{}/{}", format_sizes(&s.bet), format_sizes(&s.raise));
+                match s.max_raises {
+                    Some(cap) => format!("{}/{}", sizes, cap),
+                    None => sizes,
+                }
+            })
+            .collect();
+        write!(
+            f,
+            "pot={} stack={} raises={} allin={} threshold={} sizes={}",
+            self.pot,
+            self.stack,
+            self.max_raises,
+            self.all_in,
+            self.all_in_threshold,
+            streets.join(";")
+        )
+    }
+}
+
+impl FromStr for TreeConfig {
+    type Err = String;
+
+    /// Parse the `Display` form; missing keys keep their default.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut config = TreeConfig::default();
+        for pair in s.split_whitespace() {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got {:?}", pair))?;
+            let bad = || format!("bad value for {}: {:?}", key, value);
+            match key {
+                "pot" => config.pot = value.parse().map_err(|_| bad())?,
+                "stack" => config.stack = value.parse().map_err(|_| bad())?,
+                "raises" => config.max_raises = value.parse().map_err(|_| bad())?,
+                "allin" => config.all_in = value.parse().map_err(|_| bad())?,
+                "threshold" => config.all_in_threshold = value.parse().map_err(|_| bad())?,
+                "sizes" => {
+                    config.streets = value
+                        .split(';')
+                        .map(|street| {
+                            let parts: Vec<&str> = street.split('/').collect();
+                            if parts.len() < 2 || parts.len() > 3 {
+                                return Err(format!("bad street sizes {:?}", street));
+                            }
+                            let mut sizes =
+                                StreetSizes::new(&parse_sizes(parts[0])?, &parse_sizes(parts[1])?);
+                            if let Some(cap) = parts.get(2) {
+                                sizes.max_raises = Some(cap.parse().map_err(|_| bad())?);
+                            }
+                            Ok(sizes)
+                        })
+                        .collect::<Result<_, String>>()?
+                }
+                _ => return Err(format!("unknown key {:?}", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Fold,
+    Check,
+    Call,
+    /// Bet, with the bettor's total commitment afterwards.
+    Bet(f64),
+    /// Raise to the given total commitment.
+    Raise(f64),
+    /// All chips in, with the total commitment afterwards.
+    AllIn(f64),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Fold => write!(f, "fold"),
+            Action::Check => write!(f, "check"),
+            Action::Call => write!(f, "call"),
+            Action::Bet(to) => write!(f, "bet {}", to),
+            Action::Raise(to) => write!(f, "raise {}", to),
+            Action::AllIn(to) => write!(f, "all-in {}", to),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Action {
+        player: usize,
+        actions: Vec<Action>,
+        children: Vec<usize>,
+    },
+    /// The next street's card is dealt, then play continues at `child`.
+    Chance {
+        child: usize,
+    },
+    /// `folder` gave up the pot.
+    Fold {
+        folder: usize,
+    },
+    Showdown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    /// Chips each player has put in since the subgame started.
+    pub commit: [f64; 2],
+    /// Street of the node, 0 being the first street of the subgame. Chance
+    /// nodes belong to the street they close.
+    pub street: usize,
+}
+
+/// Betting tree of a subgame, stored as an arena with the root at 0. The
+/// tree is shared by every card dealt at its chance nodes. Its serialized
+/// form is the text form of its `config`, which `new` rebuilds it from
+/// node for node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionTree {
+    pub config: TreeConfig,
+    pub nodes: Vec<Node>,
+}
+
+impl ActionTree {
+    pub fn new(config: TreeConfig) -> Result<Self, String> {
+        if config.pot <= 0.0 || config.stack < 0.0 {
+            return Err("pot must be positive and stack non-negative".into());
+        }
+        if !(config.all_in_threshold > 0.0 && config.all_in_threshold <= 1.0) {
+            return Err("all-in threshold must be in (0, 1]".into());
+        }
+        if config.streets.is_empty() {
+            return Err("at least one street is needed".into());
+        }
+        if config
+            .streets
+            .iter()
+            .flat_map(|s| s.bet.iter().chain(&s.raise))
+            .any(|&s| s <= 0.0)
+        {
+            return Err("bet and raise sizes must be positive".into());
+        }
+        let mut tree = Self {
+            config,
+            nodes: Vec::new(),
+        };
+        tree.street([0.0, 0.0], OOP, 0, 0);
+        Ok(tree)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Chips in the pot at `node`.
+    pub fn pot(&self, node: usize) -> f64 {
+        let c = self.nodes[node].commit;
+        self.config.pot + c[0] + c[1]
+    }
+
+    /// Action `action` of `node` as a player would say it, with bets and
+    /// raises sized as a share of the pot: "check", "bet 75%", "raise 100%".
+    pub fn label(&self, node: usize, action: usize) -> Option<String> {
+        let NodeKind::Action {
+            player, actions, ..
+        } = &self.nodes.get(node)?.kind
+        else {
+            return None;
+        };
+        let commit = self.nodes[node].commit;
+        Some(match *actions.get(action)? {
+            Action::Bet(to) => {
+                let share = (to - commit[*player]) / self.pot(node);
+                format!("bet {:.0}%", share * 100.0)
+            }
+            Action::Raise(to) => {
+                let call = commit[1 - *player];
+                let share = (to - call) / (self.config.pot + 2.0 * call);
+                format!("raise {:.0}%", share * 100.0)
+            }
+            a => a.to_string(),
+        })
+    }
+
+    /// Nodes where a player acts.
+    pub fn action_nodes(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Action { .. }))
+            .count()
+    }
+
+    /// Folds and showdowns.
+    pub fn terminal_nodes(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Fold { .. } | NodeKind::Showdown))
+            .count()
+    }
+
+    /// Nodes where a card is dealt.
+    pub fn chance_nodes(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Chance { .. }))
+            .count()
+    }
+
+    fn push(&mut self, kind: NodeKind, commit: [f64; 2], street: usize) -> usize {
+        self.nodes.push(Node {
+            kind,
+            commit,
+            street,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Sized bets or raises for `player`, capped at all-in and deduplicated.
+    fn sizes(&self, commit: [f64; 2], player: usize, raise: bool, street: usize) -> Vec<Action> {
+        let stack = self.config.stack;
+        let call = commit[1 - player];
+        let sizes = &self.config.streets[street];
+        let mut targets: Vec<f64> = if raise {
+            let after_call = self.config.pot + 2.0 * call;
+            sizes.raise.iter().map(|f| call + f * after_call).collect()
+        } else {
+            let pot = self.config.pot + commit[0] + commit[1];
+            sizes.bet.iter().map(|f| commit[player] + f * pot).collect()
+        };
+        if self.config.all_in {
+            targets.push(stack);
+        }
+        let mut out: Vec<Action> = Vec::new();
+        for to in targets {
+            let action = if to >= stack - 1e-9 || to >= stack * self.config.all_in_threshold {
+                Action::AllIn(stack)
+            } else if raise {
+                Action::Raise(to)
+            } else {
+                Action::Bet(to)
+            };
+            if !out.contains(&action) {
+                out.push(action);
+            }
+        }
+        out.sort_by(|a, b| amount(a).total_cmp(&amount(b)));
+        out
+    }
+
+    /// Close `street` with equal commitments: showdown after the last
+    /// street, otherwise deal the next card. Once a player is all-in the
+    /// remaining cards are dealt without betting.
+    fn street_end(&mut self, commit: [f64; 2], street: usize) -> usize {
+        if street + 1 == self.config.streets.len() {
+            return self.push(NodeKind::Showdown, commit, street);
+        }
+        let id = self.push(NodeKind::Showdown, commit, street);
+        let child = if commit[0] >= self.config.stack - 1e-9 {
+            self.street_end(commit, street + 1)
+        } else {
+            self.street(commit, OOP, 0, street + 1)
+        };
+        self.nodes[id].kind = NodeKind::Chance { child };
+        id
+    }
+
+    /// Build `street` where `player` acts with `raises` bets made so far.
+    fn street(&mut self, commit: [f64; 2], player: usize, raises: u32, street: usize) -> usize {
+        let id = self.push(NodeKind::Showdown, commit, street);
+        let opponent = 1 - player;
+        let facing = commit[opponent] > commit[player];
+        let cap = self.config.streets[street]
+            .max_raises
+            .unwrap_or(self.config.max_raises);
+        let can_raise = raises < cap
+            && commit[opponent] < self.config.stack
+            && commit[player] < self.config.stack;
+        let mut actions = Vec::new();
+        let mut children = Vec::new();
+        if facing {
+            actions.push(Action::Fold);
+            children.push(self.push(NodeKind::Fold { folder: player }, commit, street));
+            actions.push(Action::Call);
+            children.push(self.street_end([commit[opponent]; 2], street));
+        } else {
+            actions.push(Action::Check);
+            children.push(if player == OOP {
+                self.street(commit, opponent, raises, street)
+            } else {
+                self.street_end(commit, street)
+            });
+        }
+        if can_raise {
+            for action in self.sizes(commit, player, facing, street) {
+                let mut next = commit;
+                next[player] = amount(&action);
+                actions.push(action);
+                children.push(self.street(next, opponent, raises + 1, street));
+            }
+        }
+        self.nodes[id].kind = NodeKind::Action {
+            player,
+            actions,
+            children,
+        };
+        id
+    }
+}
+
+/// Commitment after a sized action.
+fn amount(action: &Action) -> f64 {
+    match *action {
+        Action::Bet(to) | Action::Raise(to) | Action::AllIn(to) => to,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(bets: &[f64], raises: &[f64], max_raises: u32) -> TreeConfig {
+        TreeConfig {
+            pot: 100.0,
+            stack: 1000.0,
+            streets: vec![StreetSizes::new(bets, raises)],
+            max_raises,
+            all_in: false,
+            all_in_threshold: 1.0,
+        }
+    }
+
+    #[test]
+    fn check_down_only() {
+        let tree = ActionTree::new(config(&[], &[], 0)).unwrap();
+        // check, check, showdown
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.action_nodes(), 2);
+    }
+
+    #[test]
+    fn single_bet_size() {
+        let tree = ActionTree::new(config(&[0.5], &[], 1)).unwrap();
+        match &tree.nodes[0].kind {
+            NodeKind::Action { actions, .. } => {
+                assert_eq!(actions, &[Action::Check, Action::Bet(50.0)])
+            }
+            _ => panic!("root must be an action node"),
+        }
+        // OOP bet: fold or call; OOP check then IP check or bet: fold or call
+        assert_eq!(tree.action_nodes(), 4);
+        assert_eq!(tree.len(), 4 + 5);
+    }
+
+    #[test]
+    fn raises_are_capped_by_stack() {
+        let mut c = config(&[1.0], &[1.0], 10);
+        c.stack = 250.0;
+        let tree = ActionTree::new(c).unwrap();
+        let all_in = tree
+            .nodes
+            .iter()
+            .filter_map(|n| match &n.kind {
+                NodeKind::Action { actions, .. } => Some(actions),
+                _ => None,
+            })
+            .flatten()
+            .filter(|a| matches!(a, Action::AllIn(_)))
+            .count();
+        assert!(all_in > 0);
+        assert!(tree
+            .nodes
+            .iter()
+            .all(|n| n.commit[0] <= 250.0 && n.commit[1] <= 250.0));
+        // bet 100, raise to 400 is capped to all-in 250
+        match &tree.nodes[0].kind {
+            NodeKind::Action { children, .. } => match &tree.nodes[children[1]].kind {
+                NodeKind::Action { actions, .. } => {
+                    assert_eq!(actions, &[Action::Fold, Action::Call, Action::AllIn(250.0)])
+                }
+                _ => panic!("bet must be answered"),
+            },
+            _ => panic!("root must be an action node"),
+        }
+        assert_eq!(tree.label(0, 1).as_deref(), Some("bet 100%"));
+        assert_eq!(tree.label(0, 2), None);
+        let mut c = config(&[0.75], &[1.0], 2);
+        c.stack = 1000.0;
+        let tree = ActionTree::new(c).unwrap();
+        let facing = match &tree.nodes[0].kind {
+            NodeKind::Action { children, .. } => children[1],
+            _ => unreachable!(),
+        };
+        // facing 75, a pot sized raise goes to 75 + 250
+        assert_eq!(tree.label(facing, 2).as_deref(), Some("raise 100%"));
+        assert_eq!(tree.label(facing, 0).as_deref(), Some("fold"));
+    }
+
+    #[test]
+    fn streets_are_joined_by_chance_nodes() {
+        let mut c = config(&[], &[], 0);
+        c.streets.push(StreetSizes::new(&[], &[]));
+        let tree = ActionTree::new(c).unwrap();
+        // check, check, deal, check, check, showdown
+        assert_eq!(tree.len(), 6);
+        assert_eq!(tree.chance_nodes(), 1);
+        assert_eq!(tree.nodes[2].kind, NodeKind::Chance { child: 3 });
+        assert_eq!(tree.nodes[2].street, 0);
+        assert_eq!(tree.nodes[3].street, 1);
+        assert_eq!(tree.nodes[5].street, 1);
+    }
+
+    #[test]
+    fn all_in_runs_out_the_board() {
+        let mut c = config(&[], &[], 1);
+        c.stack = 100.0;
+        c.all_in = true;
+        c.streets = vec![StreetSizes::new(&[], &[]); 3];
+        let tree = ActionTree::new(c).unwrap();
+        let called = match &tree.nodes[0].kind {
+            NodeKind::Action {
+                actions, children, ..
+            } => {
+                assert_eq!(actions, &[Action::Check, Action::AllIn(100.0)]);
+                match &tree.nodes[children[1]].kind {
+                    NodeKind::Action { children, .. } => children[1],
+                    _ => panic!("all-in must be answered"),
+                }
+            }
+            _ => panic!("root must be an action node"),
+        };
+        // call, deal the turn, deal the river, showdown
+        let turn = match tree.nodes[called].kind {
+            NodeKind::Chance { child } => child,
+            _ => panic!("call must deal the turn"),
+        };
+        let river = match tree.nodes[turn].kind {
+            NodeKind::Chance { child } => child,
+            _ => panic!("no betting once all-in"),
+        };
+        assert_eq!(tree.nodes[river].kind, NodeKind::Showdown);
+        assert_eq!(tree.nodes[river].street, 2);
+    }
+
+    #[test]
+    fn all_in_threshold_and_street_caps() {
+        let mut c = config(&[0.5, 1.0], &[], 1);
+        c.stack = 150.0;
+        c.all_in_threshold = 0.6;
+        let tree = ActionTree::new(c.clone()).unwrap();
+        match &tree.nodes[0].kind {
+            NodeKind::Action { actions, .. } => {
+                // a pot sized bet would commit two thirds of the stack
+                assert_eq!(
+                    actions,
+                    &[Action::Check, Action::Bet(50.0), Action::AllIn(150.0)]
+                )
+            }
+            _ => panic!("root must be an action node"),
+        }
+        c.streets[0].max_raises = Some(0);
+        assert_eq!(ActionTree::new(c).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn config_round_trips_through_text() {
+        let mut c = TreeConfig {
+            pot: 60.0,
+            stack: 470.5,
+            all_in_threshold: 0.67,
+            ..TreeConfig::default()
+        };
+        c.streets.push(StreetSizes::new(&[0.75], &[]));
+        c.streets[1].max_raises = Some(2);
+        let text = c.to_string();
+        assert_eq!(
+            text,
+            "pot=60 stack=470.5 raises=3 allin=true threshold=0.67 sizes=33,75/100;75//2"
+        );
+        assert_eq!(text.parse::<TreeConfig>().unwrap(), c);
+        let partial: TreeConfig = "stack=200 sizes=50/".parse().unwrap();
+        assert_eq!(partial.stack, 200.0);
+        assert_eq!(partial.streets, vec![StreetSizes::new(&[0.5], &[])]);
+        assert!("pot=x".parse::<TreeConfig>().is_err());
+        assert!("sizes=50".parse::<TreeConfig>().is_err());
+        assert!("depth=3".parse::<TreeConfig>().is_err());
+    }
+
+    #[test]
+    fn tree_rebuilds_from_config_text() {
+        let mut c = config(&[0.5, 1.25], &[0.75], 3);
+        c.all_in_threshold = 0.8;
+        c.streets.push(StreetSizes::new(&[0.66], &[1.0]));
+        c.streets[1].max_raises = Some(1);
+        let tree = ActionTree::new(c).unwrap();
+        let text = tree.config.to_string();
+        let rebuilt = ActionTree::new(text.parse().unwrap()).unwrap();
+        assert_eq!(rebuilt, tree);
+    }
+
+    #[test]
+    fn rejects_bad_config() {
+        assert!(ActionTree::new(config(&[-0.5], &[], 1)).is_err());
+        let mut c = config(&[], &[], 0);
+        c.pot = 0.0;
+        assert!(ActionTree::new(c).is_err());
+    }
+}
@@ -8,9 +8,9 @@ pub fn prettify(hand: &str) -> String {
         ('d', String::from("♦")),
         ('h', String::from("♥")),
     ]);
-    return hand.chars().fold(String::new(), |acc, x| {
+    hand.chars().fold(String::new(), |acc, x| {
         acc + map.get(&x).unwrap_or(&String::from(x))
-    });
+    })
 }
 
 #[cfg(test)]